@@ -2,7 +2,7 @@ use std::time::SystemTime;
 
 use crate::c_request::write_signed_headers;
 use crate::string_to_sign::write_scope;
-use crate::util::VecExt;
+use crate::util::{ord_ignore_case, VecExt};
 use crate::vocab::{AwsRegion, AwsService, Signature};
 
 pub fn generate(
@@ -14,6 +14,11 @@ pub fn generate(
     access_key_id: &[u8; 20],
     signature: &Signature,
 ) {
+    // Presigned requests have the date in the query instead
+    debug_assert!(headers
+        .iter()
+        .any(|(key, _)| ord_ignore_case(key, b"x-amz-date").is_eq()));
+
     buffer.extend_from_slice(b"AWS4-HMAC-SHA256 Credential=");
     buffer.extend_from_slice(access_key_id);
     buffer.push(b'/');
@@ -44,7 +49,7 @@ fn test_auth_header() {
         key_date,
         AwsRegion::UsEast1,
         AwsService::Iam,
-        &key_id,
+        key_id,
         &signature,
     );
 
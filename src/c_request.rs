@@ -1,7 +1,11 @@
 use std::cmp::Ordering;
+use std::fmt;
 
+use super::util::url_encode;
 use super::util::{is_sorted_by, ord_ignore_case, ord_url_encoded, whitespace, SliceExt, VecExt};
-use super::vocab::{Hash, HttpMethod};
+#[cfg(test)]
+use super::vocab::Hash;
+use super::vocab::HttpMethod;
 
 pub struct QueryIter<'a>(&'a [u8]);
 
@@ -9,7 +13,7 @@ impl<'a> Iterator for QueryIter<'a> {
     type Item = (&'a [u8], &'a [u8]);
 
     fn next(&mut self) -> Option<(&'a [u8], &'a [u8])> {
-        if self.0.len() == 0 {
+        if self.0.is_empty() {
             return None;
         }
         let mut split = self.0.splitn(2, |&b| b == b'&');
@@ -27,29 +31,37 @@ impl<'a> Iterator for QueryIter<'a> {
     }
 }
 
-// TODO: error handling
-pub fn split_url(mut url: &[u8]) -> (&[u8], &[u8], QueryIter<'_>) {
-    if url.starts_with(b"https://") {
-        url = &url[b"https://".len()..];
-    } else {
-        panic!()
+/// Not an `https://` or `wss://` URL with a path.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ParseUrlError;
+
+impl fmt::Display for ParseUrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("not an https:// or wss:// URL with a path")
     }
-    let split_idx = url
+}
+
+impl std::error::Error for ParseUrlError {}
+
+/// Splits an `https://` or `wss://` URL into its host, path and query.
+pub fn split_url(url: &[u8]) -> Result<(&[u8], &[u8], QueryIter<'_>), ParseUrlError> {
+    let url = [&b"https://"[..], b"wss://"]
         .iter()
-        .position(|&b| b == b'/')
-        .expect("no domain/path separator");
+        .find_map(|scheme| url.strip_prefix(*scheme))
+        .ok_or(ParseUrlError)?;
+    let split_idx = url.iter().position(|&b| b == b'/').ok_or(ParseUrlError)?;
     let domain = &url[..split_idx];
     let path = &url[split_idx..];
     let mut split = path.splitn(2, |&b| b == b'?');
-    let abspath = split.next().unwrap();
+    let abspath = split.next().expect("succeeds at least once");
     let query = split.next().unwrap_or(b"");
 
-    (domain, abspath, QueryIter(query))
+    Ok((domain, abspath, QueryIter(query)))
 }
 
 #[test]
 fn test_split_url() {
-    let (domain, abspath, query) = split_url(&b"https://data.dev.m-haihon.r-crois.jp.s3.amazonaws.com/uploads/uploaded_file/sheet/478/Jisseki.zip"[..]);
+    let (domain, abspath, query) = split_url(&b"https://data.dev.m-haihon.r-crois.jp.s3.amazonaws.com/uploads/uploaded_file/sheet/478/Jisseki.zip"[..]).unwrap();
     assert_eq!(
         domain,
         &b"data.dev.m-haihon.r-crois.jp.s3.amazonaws.com"[..]
@@ -59,6 +71,76 @@ fn test_split_url() {
         &b"/uploads/uploaded_file/sheet/478/Jisseki.zip"[..]
     );
     assert_eq!(query.collect::<Vec<_>>().len(), 0);
+
+    let (domain, abspath, query) = split_url(
+        &b"wss://transcribestreaming.us-east-1.amazonaws.com:8443/stream-transcription-websocket?language-code=en-US&sample-rate=16000"[..],
+    )
+    .unwrap();
+    assert_eq!(
+        domain,
+        &b"transcribestreaming.us-east-1.amazonaws.com:8443"[..]
+    );
+    assert_eq!(abspath, &b"/stream-transcription-websocket"[..]);
+    assert_eq!(
+        query.collect::<Vec<_>>(),
+        vec![
+            (&b"language-code"[..], &b"en-US"[..]),
+            (&b"sample-rate"[..], &b"16000"[..])
+        ]
+    );
+
+    assert!(split_url(b"http://example.com/").is_err());
+    assert!(split_url(b"ws://example.com/").is_err());
+    assert!(split_url(b"https://example.com").is_err());
+}
+
+/// The URL-decoded parameters of a query string, which the canonical request
+/// encodes again.
+pub(crate) struct DecodedQuery(Vec<(Vec<u8>, Vec<u8>)>);
+
+impl DecodedQuery {
+    /// Decodes the parameters, skipping those without a name.
+    pub(crate) fn new(params: QueryIter<'_>) -> DecodedQuery {
+        let params = params
+            .filter(|(key, _)| !key.is_empty())
+            .map(|(key, val)| {
+                let mut decoded_key = Vec::new();
+                url_encode::decode(&mut decoded_key, key);
+                let mut decoded_val = Vec::new();
+                url_encode::decode(&mut decoded_val, val);
+                (decoded_key, decoded_val)
+            })
+            .collect();
+        DecodedQuery(params)
+    }
+
+    pub(crate) fn parse(query: &[u8]) -> DecodedQuery {
+        DecodedQuery::new(QueryIter(query))
+    }
+
+    /// The parameters in the order of the canonical request.
+    pub(crate) fn sorted(&self) -> Vec<(&[u8], &[u8])> {
+        let mut params: Vec<(&[u8], &[u8])> = self
+            .0
+            .iter()
+            .map(|(key, val)| (&key[..], &val[..]))
+            .collect();
+        ensure_query_order(&mut params);
+        params
+    }
+}
+
+#[test]
+fn test_decoded_query() {
+    let query = DecodedQuery::parse(b"b=2&&a%20b=c%2Fd&=x&flag");
+    assert_eq!(
+        query.sorted(),
+        vec![
+            (&b"a b"[..], &b"c/d"[..]),
+            (&b"b"[..], &b"2"[..]),
+            (&b"flag"[..], &b""[..])
+        ]
+    );
 }
 
 pub fn ensure_query_order(slice: &mut [(&[u8], &[u8])]) {
@@ -77,9 +159,6 @@ pub fn write_signed_headers(buffer: &mut Vec<u8>, headers: &[(&[u8], &[u8])]) {
     /*    debug_assert!(headers
     .iter()
     .any(|(key, _)| ord_ignore_case(key, b"x-amz-content-sha256") == Ordering::Equal));*/
-    debug_assert!(headers
-        .iter()
-        .any(|(key, _)| ord_ignore_case(key, b"x-amz-date") == Ordering::Equal));
     // Check that the headers are in correct order
     debug_assert!(is_sorted_by(headers, |(lkey, _), (rkey, _)| {
         ord_ignore_case(lkey, rkey)
@@ -94,26 +173,40 @@ pub fn write_signed_headers(buffer: &mut Vec<u8>, headers: &[(&[u8], &[u8])]) {
         buffer.push_lowercase(key);
         buffer.push(b';');
     }
-    if headers.len() > 0 {
+    if !headers.is_empty() {
         let _ = buffer.pop(); // Popping off the last '&'
     }
 }
 
+/// The path of a request, as it goes in the canonical request.
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum AbsPath<'a> {
+    /// Not URL-encoded; the canonical request encodes it.
+    Decoded(&'a [u8]),
+    /// Already URL-encoded, and signed as it is.
+    Encoded(&'a [u8]),
+}
+
 // TODO: try to input to hash function directly and compare performance
-pub fn generate(
+pub(crate) fn generate(
     buffer: &mut Vec<u8>,
     http_method: HttpMethod,
-    abspath: &[u8],
+    abspath: AbsPath<'_>,
     query: &[(&[u8], &[u8])],
     headers: &[(&[u8], &[u8])],
-    payload_hash: &Hash,
+    payload_hash: &[u8],
 ) {
     // HTTP Verb
     buffer.extend_from_slice(http_method.to_bytes());
     buffer.push(b'\n');
 
     // Canonical URI
-    buffer.push_url_encoded_preserve_paths(abspath);
+    match abspath {
+        AbsPath::Decoded(abspath) => {
+            buffer.push_url_encoded_preserve_paths(abspath);
+        }
+        AbsPath::Encoded(abspath) => buffer.extend_from_slice(abspath),
+    }
     buffer.push(b'\n');
 
     // Canonical Query String
@@ -127,7 +220,7 @@ pub fn generate(
         buffer.push_url_encoded(val);
         buffer.push(b'&');
     }
-    if query.len() > 0 {
+    if !query.is_empty() {
         let _ = buffer.pop(); // Popping off the last '&'
     }
     buffer.push(b'\n');
@@ -146,7 +239,7 @@ pub fn generate(
     buffer.push(b'\n');
 
     // Hashed Payload
-    buffer.extend_from_slice(payload_hash.as_hex());
+    buffer.extend_from_slice(payload_hash);
 }
 
 #[test]
@@ -171,10 +264,10 @@ fn test_generate_c_request() {
     generate(
         &mut buffer,
         HttpMethod::Get,
-        b"/uploads/uploaded_file/sheet/478/Jisseki.zip",
+        AbsPath::Decoded(b"/uploads/uploaded_file/sheet/478/Jisseki.zip"),
         &[],
         &signed_headers,
-        &hash,
+        hash.as_hex(),
     );
 
     println!("Canonical Request:\n{}", String::from_utf8_lossy(&buffer));
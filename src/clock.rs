@@ -0,0 +1,235 @@
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::util::xml::find_element;
+use crate::util::{ParseTime, ParseTimeError};
+
+/// Where the current time comes from. Everything that needs the current
+/// time consults one of these instead of calling `SystemTime::now()`, so that
+/// signing can be made deterministic.
+pub trait TimeSource: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// The system clock.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemClock;
+
+impl TimeSource for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A time source that's stuck at a single point in time.
+#[derive(Copy, Clone, Debug)]
+pub struct FixedTime(pub SystemTime);
+
+impl TimeSource for FixedTime {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
+
+/// A time source that only moves when told to.
+#[derive(Debug)]
+pub struct ManualTime(Mutex<SystemTime>);
+
+impl ManualTime {
+    pub fn new(start: SystemTime) -> ManualTime {
+        ManualTime(Mutex::new(start))
+    }
+
+    pub fn set(&self, time: SystemTime) {
+        *self.0.lock().expect("never poisoned") = time;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.0.lock().expect("never poisoned") += duration;
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> SystemTime {
+        *self.0.lock().expect("never poisoned")
+    }
+}
+
+impl<T: TimeSource + ?Sized> TimeSource for Arc<T> {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+}
+
+/// A clock that corrects the time of a time source with an offset learned
+/// from the server, so that requests keep getting accepted even if the local
+/// clock has drifted.
+pub struct Clock {
+    source: Arc<dyn TimeSource>,
+    skew_millis: AtomicI64,
+}
+
+impl fmt::Debug for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Clock")
+            .field("source_now", &self.source.now())
+            .field("skew_millis", &self.skew_millis())
+            .finish()
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::new()
+    }
+}
+
+impl Clock {
+    pub fn new() -> Clock {
+        Clock::with_source(Arc::new(SystemClock))
+    }
+
+    pub fn with_source(source: Arc<dyn TimeSource>) -> Clock {
+        Clock {
+            source,
+            skew_millis: AtomicI64::new(0),
+        }
+    }
+
+    pub fn now(&self) -> SystemTime {
+        apply_skew(self.source.now(), self.skew_millis())
+    }
+
+    /// How far ahead of the local clock the server is, in milliseconds.
+    /// Negative if the local clock is ahead.
+    pub fn skew_millis(&self) -> i64 {
+        self.skew_millis.load(Ordering::Relaxed)
+    }
+
+    pub fn set_skew_millis(&self, skew_millis: i64) {
+        self.skew_millis.store(skew_millis, Ordering::Relaxed);
+    }
+
+    /// Learns the skew from a timestamp the server reported as its current time.
+    pub fn update_from_server_time(&self, server_time: SystemTime) {
+        let local_time = self.source.now();
+        let skew_millis = match server_time.duration_since(local_time) {
+            Ok(ahead) => ahead.as_millis() as i64,
+            Err(behind) => -(behind.duration().as_millis() as i64),
+        };
+        self.set_skew_millis(skew_millis);
+    }
+
+    /// Learns the skew from the value of a HTTP `Date` response header.
+    pub fn update_from_date_header(&self, date: &[u8]) -> Result<(), ParseTimeError> {
+        self.update_from_server_time(SystemTime::parse_http_date(date)?);
+        Ok(())
+    }
+
+    /// Learns the skew from the body of an error response, if it's a
+    /// `RequestTimeTooSkewed` error. Returns whether the skew was updated.
+    pub fn update_from_error_response(&self, body: &[u8]) -> bool {
+        if find_element(body, b"Code") != Some(b"RequestTimeTooSkewed") {
+            return false;
+        }
+        let server_time = find_element(body, b"ServerTime")
+            .and_then(|time| SystemTime::parse_iso8601_extended(time).ok());
+        if let Some(server_time) = server_time {
+            self.update_from_server_time(server_time);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl TimeSource for Clock {
+    fn now(&self) -> SystemTime {
+        Clock::now(self)
+    }
+}
+
+fn apply_skew(time: SystemTime, skew_millis: i64) -> SystemTime {
+    let skew = Duration::from_millis(skew_millis.unsigned_abs());
+    if skew_millis >= 0 {
+        time + skew
+    } else {
+        time - skew
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    // 20191010T210709Z
+    const LOCAL_TIME: u64 = 1570741629;
+
+    fn test_clock() -> (Arc<ManualTime>, Clock) {
+        let time = Arc::new(ManualTime::new(
+            UNIX_EPOCH + Duration::from_secs(LOCAL_TIME),
+        ));
+        let clock = Clock::with_source(time.clone());
+        (time, clock)
+    }
+
+    #[test]
+    fn test_manual_time() {
+        let (time, clock) = test_clock();
+        time.advance(Duration::from_secs(5));
+        assert_eq!(
+            clock.now(),
+            UNIX_EPOCH + Duration::from_secs(LOCAL_TIME + 5)
+        );
+        time.set(UNIX_EPOCH);
+        assert_eq!(clock.now(), UNIX_EPOCH);
+    }
+
+    #[test]
+    fn test_now_applies_skew() {
+        let (_, clock) = test_clock();
+        clock.set_skew_millis(-3_600_000);
+        assert_eq!(
+            clock.now(),
+            UNIX_EPOCH + Duration::from_secs(LOCAL_TIME - 3600)
+        );
+        clock.set_skew_millis(1_500);
+        assert_eq!(
+            clock.now(),
+            UNIX_EPOCH + Duration::from_millis(LOCAL_TIME * 1000 + 1_500)
+        );
+    }
+
+    #[test]
+    fn test_update_from_error_response() {
+        let (_, clock) = test_clock();
+        let body = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<Error><Code>RequestTimeTooSkewed</Code><Message>The difference between the request time and the current time is too large.</Message><RequestTime>20191010T210709Z</RequestTime><ServerTime>2019-10-10T21:27:09Z</ServerTime><MaxAllowedSkewMilliseconds>900000</MaxAllowedSkewMilliseconds></Error>";
+        assert!(clock.update_from_error_response(body));
+        assert_eq!(clock.skew_millis(), 1_200_000);
+
+        clock.set_skew_millis(0);
+        assert!(!clock.update_from_error_response(
+            b"<Error><Code>AccessDenied</Code><ServerTime>2019-10-10T21:27:09Z</ServerTime></Error>"
+        ));
+        assert!(!clock.update_from_error_response(
+            b"<Error><Code>RequestTimeTooSkewed</Code><ServerTime>now</ServerTime></Error>"
+        ));
+        assert_eq!(clock.skew_millis(), 0);
+    }
+
+    #[test]
+    fn test_update_from_date_header() {
+        let (_, clock) = test_clock();
+        clock
+            .update_from_date_header(b"Thu, 10 Oct 2019 21:05:09 GMT")
+            .unwrap();
+        assert_eq!(clock.skew_millis(), -120_000);
+
+        assert!(clock.update_from_date_header(b"yesterday").is_err());
+        assert_eq!(clock.skew_millis(), -120_000);
+    }
+}
@@ -1,12 +1,20 @@
 use std::convert::TryInto;
+use std::fmt;
 use std::time::SystemTime;
 
-use crate::util::{FormatTime, SliceExt};
+use crate::secret::SecretAccessKey;
+use crate::util::{wipe, FormatTime};
 use crate::vocab::{AwsRegion, AwsService, Signature};
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct Key(pub(crate) ring::hmac::Key);
 
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Key(***)")
+    }
+}
+
 impl Key {
     pub fn new(secret: &[u8]) -> Key {
         Key(ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret))
@@ -18,51 +26,64 @@ impl Key {
         signature.0.copy_from_slice(hmac.as_ref());
         signature
     }
+
+    /// Checks in constant time that `tag` is the signature of `message`.
+    pub fn verify(&self, message: &[u8], tag: &[u8]) -> bool {
+        ring::hmac::verify(&self.0, message, tag).is_ok()
+    }
+}
+
+/// Derives the next key in the chain from the previous one, wiping the
+/// intermediate secret.
+fn derive(key: &Key, message: &[u8]) -> Key {
+    let mut secret = key.sign(message);
+    let key = Key::new(secret.as_bytes());
+    wipe(&mut secret.0);
+    key
 }
 
 fn signing_key_secret(
-    secret_access_key: &[u8; 40],
+    secret_access_key: &SecretAccessKey,
     key_date: SystemTime,
     key_region: AwsRegion,
     service: AwsService,
 ) -> Signature {
     let mut key_buf = *b"AWS40000000000000000000000000000000000000000";
-    let key_secret = &mut key_buf[4..];
-    key_secret.copy_from_slice(secret_access_key);
+    key_buf[4..].copy_from_slice(secret_access_key.as_bytes());
+    let key = Key::new(&key_buf[..]);
+    wipe(&mut key_buf);
     let mut date_buf = *b"00000000";
     key_date.write_yyyymmdd(&mut date_buf);
-    let key = Key::new(&key_buf[..]);
-    let date_key = Key::new(key.sign(&mut date_buf).as_bytes());
-    let date_region_key = Key::new(date_key.sign(key_region.to_bytes()).as_bytes());
-    let date_region_service_key = Key::new(date_region_key.sign(service.to_bytes()).as_bytes());
+    let date_key = derive(&key, &date_buf);
+    let date_region_key = derive(&date_key, key_region.to_bytes());
+    let date_region_service_key = derive(&date_region_key, service.to_bytes());
     date_region_service_key.sign(b"aws4_request")
 }
 
 pub fn signing_key(
-    secret_access_key: &[u8; 40],
+    secret_access_key: &SecretAccessKey,
     key_date: SystemTime,
     key_region: AwsRegion,
     service: AwsService,
 ) -> Key {
-    let key_secret = signing_key_secret(secret_access_key, key_date, key_region, service);
-    Key::new(key_secret.as_bytes())
+    let mut key_secret = signing_key_secret(secret_access_key, key_date, key_region, service);
+    let key = Key::new(key_secret.as_bytes());
+    wipe(&mut key_secret.0);
+    key
 }
 
-pub fn validate_secret_key<S>(key: &S) -> Result<&[u8; 40], ()>
+#[allow(clippy::result_unit_err)]
+pub fn validate_secret_key<S>(key: &S) -> Result<SecretAccessKey, ()>
 where
-    S: AsRef<[u8]>,
+    S: AsRef<[u8]> + ?Sized,
 {
-    let key = key.as_ref();
-    if key.len() == 40 {
-        Ok(key.into_array_40())
-    } else {
-        Err(())
-    }
+    key.as_ref().try_into().map(SecretAccessKey).map_err(|_| ())
 }
 
+#[allow(clippy::result_unit_err)]
 pub fn validate_key_id<S>(key: &S) -> Result<&[u8; 20], ()>
 where
-    S: AsRef<[u8]>,
+    S: AsRef<[u8]> + ?Sized,
 {
     key.as_ref().try_into().map_err(|_| ())
 }
@@ -75,10 +96,10 @@ mod tests {
     #[test]
     fn test_generation() {
         // Example from: https://docs.aws.amazon.com/general/latest/gr/sigv4-calculate-signature.html
-        let secret = b"wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+        let secret = validate_secret_key(b"wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY").unwrap();
         // Date: 20150830
         let key_date = UNIX_EPOCH + Duration::from_secs(1440938160);
-        let key_secret = signing_key_secret(secret, key_date, AwsRegion::UsEast1, AwsService::Iam);
+        let key_secret = signing_key_secret(&secret, key_date, AwsRegion::UsEast1, AwsService::Iam);
         let mut key_secret_buf = [0; 64];
         key_secret.write_hex(&mut key_secret_buf);
         assert_eq!(
@@ -87,6 +108,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_secret_key() {
+        assert!(validate_secret_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY").is_ok());
+        assert!(validate_secret_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLE").is_err());
+        assert_eq!(
+            format!(
+                "{:?}",
+                signing_key(
+                    &validate_secret_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY").unwrap(),
+                    UNIX_EPOCH,
+                    AwsRegion::UsEast1,
+                    AwsService::Iam,
+                )
+            ),
+            "Key(***)"
+        );
+    }
+
     #[test]
     fn test_signing() {
         // Example from: https://docs.aws.amazon.com/general/latest/gr/sigv4-calculate-signature.html
@@ -104,4 +143,14 @@ f536975d06c0309214f805bb90ccff089219ecd68b2577efef23edd43b7e1a59"[..],
             &b"5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"[..]
         );
     }
+
+    #[test]
+    fn test_verify() {
+        let test_key = Key::new(b"key");
+        let signature = test_key.sign(b"message");
+        assert!(test_key.verify(b"message", signature.as_bytes()));
+        assert!(!test_key.verify(b"massage", signature.as_bytes()));
+        assert!(!test_key.verify(b"message", &signature.as_bytes()[..31]));
+        assert!(!Key::new(b"other key").verify(b"message", signature.as_bytes()));
+    }
 }
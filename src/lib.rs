@@ -1,17 +1,60 @@
 use std::time::SystemTime;
 
+use c_request::AbsPath;
+use util::iso8601::BASIC_FORMAT_SEC_UTC;
+
+mod api_endpoint;
 mod auth_header;
 mod c_request;
+mod clock;
+pub mod credentials;
+mod eks;
+mod elasticache;
 mod keys;
+#[cfg(any(feature = "tower", feature = "reqwest-middleware"))]
+pub mod middleware;
+mod msk;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+mod post_policy;
+mod rds;
+mod s3;
+mod secret;
+mod signer;
 mod string_to_sign;
+mod transport;
 mod util;
 mod vocab;
+mod websocket;
 
-pub use c_request::{ensure_header_order, ensure_query_order, split_url};
+pub use api_endpoint::{ApiEndpoint, ApiUrlError};
+pub use c_request::{ensure_header_order, ensure_query_order, split_url, ParseUrlError};
+pub use clock::{Clock, FixedTime, ManualTime, SystemClock, TimeSource};
+pub use credentials::{Credentials, CredentialsError, ProvideCredentials};
+pub use eks::eks_token;
+pub use elasticache::{
+    elasticache_auth_token, elasticache_serverless_auth_token, memorydb_auth_token,
+};
 pub use keys::{signing_key, validate_key_id, validate_secret_key, Key};
-pub use util::FormatTime;
+pub use msk::{msk_oauth_token, MskAuthPayload};
+#[cfg(feature = "async")]
+pub use nonblocking::{AsyncSigner, ProvideCredentialsAsync};
+pub use post_policy::{Condition, PostForm, PostPolicy, PostPolicyError};
+pub use rds::rds_auth_token;
+pub use s3::{MultipartUpload, PartNumberError, S3Url};
+pub use secret::{SecretAccessKey, SessionToken};
+pub use signer::{AuthHeaders, SignedRequest, Signer};
+pub use transport::{HttpClient, HttpRequest, HttpResponse, PlainHttpClient};
+pub use util::{FormatTime, ParseHexError, ParseTime, ParseTimeError};
 pub use vocab::{AwsRegion, AwsService, Hash, HttpMethod, Signature};
+pub use websocket::{iot_websocket_url, presign_websocket_url, transcribe_websocket_url};
 
+/// Generates the `Authorization` header value with explicit request and
+/// signing key times.
+#[deprecated(
+    note = "use gen_auth_header_with_time_source, or Signer, which take the time from a TimeSource"
+)]
+#[allow(clippy::too_many_arguments)]
 pub fn gen_auth_header<'a>(
     buffer: &'a mut Vec<u8>,
     http_method: HttpMethod,
@@ -26,15 +69,89 @@ pub fn gen_auth_header<'a>(
     key_date: SystemTime,
     payload_hash: &Hash,
 ) -> &'a [u8] {
-    let offset = buffer.len();
-    c_request::generate(
+    gen_auth_header_for_payload(
         buffer,
         http_method,
-        abspath,
+        AbsPath::Decoded(abspath),
         query,
         signed_headers,
-        payload_hash,
+        signing_key,
+        key_id,
+        region,
+        service,
+        request_time,
+        key_date,
+        payload_hash.as_hex(),
+    )
+}
+
+/// Generates the `Authorization` header value at the current time of
+/// `time`, with a signing key derived for that date. `signed_headers` leave
+/// out `x-amz-date`, which is added with that time and returned, to be sent
+/// along.
+#[allow(clippy::too_many_arguments)]
+pub fn gen_auth_header_with_time_source<'a>(
+    buffer: &'a mut Vec<u8>,
+    http_method: HttpMethod,
+    abspath: &[u8],
+    query: &[(&[u8], &[u8])],
+    signed_headers: &[(&[u8], &[u8])],
+    secret_access_key: &SecretAccessKey,
+    key_id: &[u8; 20],
+    region: AwsRegion,
+    service: AwsService,
+    time: &dyn TimeSource,
+    payload_hash: &Hash,
+) -> AuthHeaders<'a> {
+    let request_time = time.now();
+    let mut x_amz_date = BASIC_FORMAT_SEC_UTC;
+    request_time.write_iso8602_basic_seconds_utc(&mut x_amz_date);
+    let mut headers = Vec::with_capacity(signed_headers.len() + 1);
+    headers.extend_from_slice(signed_headers);
+    headers.push((&b"x-amz-date"[..], &x_amz_date[..]));
+    ensure_header_order(&mut headers);
+
+    let signing_key = signing_key(secret_access_key, request_time, region, service);
+    let authorization = gen_auth_header_for_payload(
+        buffer,
+        http_method,
+        AbsPath::Decoded(abspath),
+        query,
+        &headers,
+        &signing_key,
+        key_id,
+        region,
+        service,
+        request_time,
+        request_time,
+        payload_hash.as_hex(),
     );
+    AuthHeaders {
+        authorization,
+        x_amz_date,
+        x_amz_security_token: None,
+    }
+}
+
+/// Like `gen_auth_header`, but the payload is given as it appears in the
+/// canonical request: either the hex hash or `UNSIGNED-PAYLOAD`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn gen_auth_header_for_payload<'a>(
+    buffer: &'a mut Vec<u8>,
+    http_method: HttpMethod,
+    abspath: AbsPath<'_>,
+    query: &[(&[u8], &[u8])],
+    signed_headers: &[(&[u8], &[u8])],
+    signing_key: &Key,
+    key_id: &[u8; 20],
+    region: AwsRegion,
+    service: AwsService,
+    request_time: SystemTime,
+    key_date: SystemTime,
+    payload: &[u8],
+) -> &'a [u8] {
+    let offset = buffer.len();
+    c_request::generate(buffer, http_method, abspath, query, signed_headers, payload);
     let c_request_hash = Hash::new(&buffer[offset..]);
     buffer.truncate(offset);
     string_to_sign::generate(
@@ -58,3 +175,38 @@ pub fn gen_auth_header<'a>(
     );
     &buffer[offset..]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_gen_auth_header_with_time_source() {
+        // Example from: https://docs.aws.amazon.com/general/latest/gr/sigv4-calculate-signature.html
+        let time = FixedTime(UNIX_EPOCH + Duration::from_secs(1440938160));
+        let secret = validate_secret_key(b"wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY").unwrap();
+        let mut buffer = Vec::new();
+        let signed = gen_auth_header_with_time_source(
+            &mut buffer,
+            HttpMethod::Get,
+            b"/",
+            &[(b"Action", b"ListUsers"), (b"Version", b"2010-05-08")],
+            &[
+                (
+                    b"content-type",
+                    b"application/x-www-form-urlencoded; charset=utf-8",
+                ),
+                (b"host", b"iam.amazonaws.com"),
+            ],
+            &secret,
+            b"AKIDEXAMPLE000000000",
+            AwsRegion::UsEast1,
+            AwsService::Iam,
+            &time,
+            &Hash::new(b""),
+        );
+        assert_eq!(&signed.x_amz_date, b"20150830T123600Z");
+        assert_eq!(signed.authorization, &b"AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE000000000/20150830/us-east-1/iam/aws4_request, SignedHeaders=content-type;host;x-amz-date, Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"[..]);
+    }
+}
@@ -0,0 +1,312 @@
+//! Signing of requests in flight: a tower `Layer` for `http::Request`s, and
+//! a `Middleware` for `reqwest-middleware` clients. Both get the signer from
+//! an `AsyncSigner`, so the credentials are refreshed as they rotate.
+
+use std::fmt;
+
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use http::Method;
+
+use crate::c_request::{ensure_header_order, AbsPath, DecodedQuery};
+use crate::credentials::CredentialsError;
+use crate::signer::Signer;
+use crate::util::{whitespace, VecExt};
+use crate::vocab::{AwsService, Hash, HttpMethod, UNSIGNED_PAYLOAD};
+
+#[cfg(feature = "reqwest-middleware")]
+mod reqwest;
+#[cfg(feature = "tower")]
+mod tower;
+
+#[cfg(feature = "reqwest-middleware")]
+pub use self::reqwest::SigningMiddleware;
+#[cfg(feature = "tower")]
+pub use self::tower::{SigningLayer, SigningService};
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// How the body of a request is covered by the signature.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum PayloadMode {
+    /// The body is buffered and its hash is signed. Works with every
+    /// service.
+    #[default]
+    Signed,
+    /// The body is passed on as it is, and `UNSIGNED-PAYLOAD` is signed in
+    /// place of its hash. Only S3 accepts this.
+    Unsigned,
+}
+
+#[derive(Debug)]
+pub enum SigningError {
+    Credentials(CredentialsError),
+    UnsupportedMethod(Method),
+    InvalidRequest(&'static str),
+    /// The body couldn't be read for hashing.
+    Body(BoxError),
+    /// The body is a stream, which can't be hashed without consuming it.
+    /// Use `PayloadMode::Unsigned` for streaming uploads to S3.
+    StreamingBody,
+}
+
+impl fmt::Display for SigningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use SigningError::*;
+        match self {
+            Credentials(err) => write!(f, "no credentials for signing: {}", err),
+            UnsupportedMethod(method) => write!(f, "can't sign {} requests", method),
+            InvalidRequest(reason) => write!(f, "can't sign request: {}", reason),
+            Body(err) => write!(f, "reading the body for signing failed: {}", err),
+            StreamingBody => write!(f, "can't sign the hash of a streaming body"),
+        }
+    }
+}
+
+impl std::error::Error for SigningError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SigningError::Credentials(err) => Some(err),
+            SigningError::Body(err) => Some(&**err),
+            _ => None,
+        }
+    }
+}
+
+impl From<CredentialsError> for SigningError {
+    fn from(err: CredentialsError) -> SigningError {
+        SigningError::Credentials(err)
+    }
+}
+
+/// Signs a request given by its parts, with the path and query as they're
+/// sent. `payload` is the body if it's to be signed. Returns the headers to
+/// add to the request.
+///
+/// Besides `host` and `x-amz-content-sha256`, `content-type`, `content-md5`
+/// and any `x-amz-*` headers are signed, so that they can't be tampered
+/// with; signing hop-by-hop headers would break on proxies.
+pub(crate) fn sign_request(
+    signer: &Signer,
+    method: &Method,
+    authority: &str,
+    path: &str,
+    query: Option<&str>,
+    headers: &HeaderMap,
+    payload: Option<&[u8]>,
+) -> Result<Vec<(HeaderName, HeaderValue)>, SigningError> {
+    let http_method = match *method {
+        Method::GET => HttpMethod::Get,
+        Method::POST => HttpMethod::Post,
+        Method::PUT => HttpMethod::Put,
+        Method::DELETE => HttpMethod::Delete,
+        Method::HEAD => HttpMethod::Head,
+        Method::PATCH => HttpMethod::Patch,
+        _ => return Err(SigningError::UnsupportedMethod(method.clone())),
+    };
+    if authority.is_empty() {
+        return Err(SigningError::InvalidRequest("no host"));
+    }
+
+    // S3 signs the path as it's sent, and other services encode it once more
+    let path = if path.is_empty() { "/" } else { path };
+    let mut encoded_path = Vec::new();
+    let abspath = if signer.service() == AwsService::S3 {
+        AbsPath::Encoded(path.as_bytes())
+    } else {
+        encoded_path.push_url_encoded_preserve_paths(path.as_bytes());
+        AbsPath::Encoded(&encoded_path)
+    };
+
+    // The canonical request encodes the query itself
+    let decoded_query = DecodedQuery::parse(query.unwrap_or("").as_bytes());
+    let query = decoded_query.sorted();
+
+    let content_sha256 = match payload {
+        Some(payload) => Hash::new(payload).as_hex().to_vec(),
+        None => UNSIGNED_PAYLOAD.to_vec(),
+    };
+    let signed_values: Vec<(&str, Vec<u8>)> = headers
+        .keys()
+        .map(HeaderName::as_str)
+        .filter(|name| is_signed_header(name))
+        .map(|name| (name, header_value(headers, name)))
+        .collect();
+    let mut signed_headers: Vec<(&[u8], &[u8])> = signed_values
+        .iter()
+        .map(|(name, value)| (name.as_bytes(), &value[..]))
+        .collect();
+    signed_headers.push((b"host", authority.as_bytes()));
+    signed_headers.push((b"x-amz-content-sha256", &content_sha256));
+    ensure_header_order(&mut signed_headers);
+
+    let mut buffer = Vec::new();
+    let signed = signer.sign_payload(
+        &mut buffer,
+        http_method,
+        abspath,
+        &query,
+        &signed_headers,
+        &content_sha256,
+    );
+
+    let invalid = |_| SigningError::InvalidRequest("header value can't be sent");
+    let mut add = vec![
+        (
+            http::header::AUTHORIZATION,
+            HeaderValue::from_bytes(signed.authorization).map_err(invalid)?,
+        ),
+        (
+            HeaderName::from_static("x-amz-date"),
+            HeaderValue::from_bytes(&signed.x_amz_date).map_err(invalid)?,
+        ),
+        (
+            HeaderName::from_static("x-amz-content-sha256"),
+            HeaderValue::from_bytes(&content_sha256).map_err(invalid)?,
+        ),
+    ];
+    if let Some(token) = signed.x_amz_security_token {
+        let mut value = HeaderValue::from_bytes(token.as_bytes()).map_err(invalid)?;
+        value.set_sensitive(true);
+        add.push((HeaderName::from_static("x-amz-security-token"), value));
+    }
+    Ok(add)
+}
+
+/// The headers added by signing aren't signed themselves, whatever values
+/// they might have before.
+fn is_signed_header(name: &str) -> bool {
+    match name {
+        "content-type" | "content-md5" => true,
+        "x-amz-date" | "x-amz-security-token" | "x-amz-content-sha256" => false,
+        _ => name.starts_with("x-amz-"),
+    }
+}
+
+/// The canonical value of a header: the values of repeated headers are
+/// joined by commas, and runs of whitespace are trimmed and collapsed.
+fn header_value(headers: &HeaderMap, name: &str) -> Vec<u8> {
+    let mut canonical = Vec::new();
+    for (i, value) in headers.get_all(name).iter().enumerate() {
+        if i > 0 {
+            canonical.push(b',');
+        }
+        let words = value
+            .as_bytes()
+            .split(|&b| whitespace(b))
+            .filter(|word| !word.is_empty());
+        for (j, word) in words.enumerate() {
+            if j > 0 {
+                canonical.push(b' ');
+            }
+            canonical.extend_from_slice(word);
+        }
+    }
+    canonical
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::tests::test_signer;
+    use crate::vocab::AwsRegion;
+
+    #[test]
+    fn test_sign_request_canonicalizes() {
+        let signer = test_signer(AwsRegion::UsEast1, AwsService::S3);
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("text/plain"));
+        headers.append("x-amz-meta-tag", HeaderValue::from_static("  a   b "));
+        headers.append("x-amz-meta-tag", HeaderValue::from_static("c"));
+        headers.insert("user-agent", HeaderValue::from_static("test"));
+        headers.insert("x-amz-date", HeaderValue::from_static("stale"));
+        let added = sign_request(
+            &signer,
+            &Method::PUT,
+            "examplebucket.s3.amazonaws.com",
+            "/photos/caf%C3%A9.jpg",
+            Some("versionId=2&acl"),
+            &headers,
+            None,
+        )
+        .unwrap();
+
+        let mut buffer = Vec::new();
+        let expected = signer.sign_unsigned_payload(
+            &mut buffer,
+            HttpMethod::Put,
+            "/photos/café.jpg".as_bytes(),
+            &[(b"acl", b""), (b"versionId", b"2")],
+            &[
+                (b"content-type", b"text/plain"),
+                (b"host", b"examplebucket.s3.amazonaws.com"),
+                (b"x-amz-content-sha256", UNSIGNED_PAYLOAD),
+                (b"x-amz-meta-tag", b"a b,c"),
+            ],
+        );
+        assert_eq!(added[0].1.as_bytes(), expected.authorization);
+        assert!(std::str::from_utf8(expected.authorization)
+            .unwrap()
+            .contains(
+                "SignedHeaders=content-type;host;x-amz-content-sha256;x-amz-date;x-amz-meta-tag,"
+            ));
+        assert_eq!(added[1].1.as_bytes(), b"20150830T123600Z");
+        assert_eq!(added[2].1.as_bytes(), UNSIGNED_PAYLOAD);
+        assert_eq!(added.len(), 3);
+
+        assert!(matches!(
+            sign_request(
+                &signer,
+                &Method::OPTIONS,
+                "s3.amazonaws.com",
+                "/",
+                None,
+                &headers,
+                None
+            ),
+            Err(SigningError::UnsupportedMethod(_))
+        ));
+    }
+
+    #[test]
+    fn test_sign_request_path_encoding() {
+        let sign = |service, authority, path, payload| {
+            let signer = test_signer(AwsRegion::UsEast1, service);
+            let added = sign_request(
+                &signer,
+                &Method::GET,
+                authority,
+                path,
+                None,
+                &HeaderMap::new(),
+                payload,
+            )
+            .unwrap();
+            added[0].1.to_str().unwrap().to_owned()
+        };
+
+        // Other services sign the path encoded once more
+        assert_eq!(
+            sign(
+                AwsService::ExecuteApi,
+                "abc123.execute-api.us-east-1.amazonaws.com",
+                "/prod/items/a%20b%2Fc",
+                Some(&b""[..]),
+            ),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE000000000/20150830/us-east-1/execute-api/aws4_request, \
+SignedHeaders=host;x-amz-content-sha256;x-amz-date, \
+Signature=2a3aa41d128e11ce7528d29c4916c0d801772c746dca3767e854b1cf7517f640"
+        );
+        // S3 signs it as it is, keeping an encoded slash
+        assert_eq!(
+            sign(
+                AwsService::S3,
+                "examplebucket.s3.amazonaws.com",
+                "/photos/a%2Fb.jpg",
+                None,
+            ),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE000000000/20150830/us-east-1/s3/aws4_request, \
+SignedHeaders=host;x-amz-content-sha256;x-amz-date, \
+Signature=fb367f522c2d597883118a74baecee502f3526192fd04b8cb8e64f54e9809b06"
+        );
+    }
+}
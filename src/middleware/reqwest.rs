@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Error, Middleware, Next, Result};
+
+use super::{sign_request, PayloadMode, SigningError};
+use crate::nonblocking::AsyncSigner;
+
+/// Signs the requests of a `reqwest_middleware::ClientWithMiddleware`. Add
+/// it after any middleware that changes the request, like retries, so that
+/// every attempt is signed anew.
+///
+/// Reqwest keeps bodies built from bytes in memory, so those are signed as
+/// they are in the default `PayloadMode::Signed`. Streaming bodies are an
+/// error in that mode; use `PayloadMode::Unsigned` for them.
+pub struct SigningMiddleware {
+    signer: Arc<AsyncSigner>,
+    mode: PayloadMode,
+}
+
+impl SigningMiddleware {
+    pub fn new(signer: Arc<AsyncSigner>) -> SigningMiddleware {
+        SigningMiddleware {
+            signer,
+            mode: PayloadMode::default(),
+        }
+    }
+
+    pub fn with_payload_mode(mut self, mode: PayloadMode) -> SigningMiddleware {
+        self.mode = mode;
+        self
+    }
+
+    async fn sign(&self, request: &mut Request) -> std::result::Result<(), SigningError> {
+        let signer = self.signer.signer().await?;
+        let payload = match (self.mode, request.body()) {
+            (PayloadMode::Unsigned, _) => None,
+            (PayloadMode::Signed, None) => Some(&[][..]),
+            (PayloadMode::Signed, Some(body)) => {
+                Some(body.as_bytes().ok_or(SigningError::StreamingBody)?)
+            }
+        };
+        let url = request.url();
+        let authority = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_owned(),
+            (None, _) => String::new(),
+        };
+        let headers = sign_request(
+            &signer,
+            request.method(),
+            &authority,
+            url.path(),
+            url.query(),
+            request.headers(),
+            payload,
+        )?;
+        for (name, value) in headers {
+            request.headers_mut().insert(name, value);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for SigningMiddleware {
+    async fn handle(
+        &self,
+        mut request: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        self.sign(&mut request).await.map_err(Error::middleware)?;
+        next.run(request, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nonblocking::block_on;
+    use crate::signer::tests::{test_credentials, test_signer, test_time};
+    use crate::vocab::{AwsRegion, AwsService, Hash, HttpMethod};
+    use std::sync::Mutex;
+
+    /// Ends the chain by keeping the request instead of sending it.
+    struct Capture(Arc<Mutex<Option<Request>>>);
+
+    #[async_trait::async_trait]
+    impl Middleware for Capture {
+        async fn handle(
+            &self,
+            request: Request,
+            _: &mut Extensions,
+            _: Next<'_>,
+        ) -> Result<Response> {
+            *self.0.lock().unwrap() = Some(request);
+            Ok(Response::from(http::Response::new("")))
+        }
+    }
+
+    fn client(mode: PayloadMode) -> (reqwest_middleware::ClientWithMiddleware, Capture) {
+        let signer = AsyncSigner::new(
+            Arc::new(test_credentials()),
+            AwsRegion::EuWest1,
+            AwsService::Iam,
+        )
+        .with_time_source(test_time());
+        let captured = Arc::new(Mutex::new(None));
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(SigningMiddleware::new(Arc::new(signer)).with_payload_mode(mode))
+            .with(Capture(captured.clone()))
+            .build();
+        (client, Capture(captured))
+    }
+
+    #[test]
+    fn test_signs_request() {
+        let (client, captured) = client(PayloadMode::Signed);
+        let body = "Action=ListUsers&Version=2010-05-08";
+        let request = client
+            .post("http://localhost:8080/?Marker=a%2Bb")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .body(body)
+            .build()
+            .unwrap();
+        block_on(client.execute(request)).unwrap();
+        let request = captured.0.lock().unwrap().take().unwrap();
+
+        let signer = test_signer(AwsRegion::EuWest1, AwsService::Iam);
+        let hash = Hash::new(body.as_bytes());
+        let mut buffer = Vec::new();
+        let expected = signer.sign(
+            &mut buffer,
+            HttpMethod::Post,
+            b"/",
+            &[(b"Marker", b"a+b")],
+            &[
+                (b"content-type", b"application/x-www-form-urlencoded"),
+                (b"host", b"localhost:8080"),
+                (b"x-amz-content-sha256", hash.as_hex()),
+            ],
+            &hash,
+        );
+        assert_eq!(
+            request.headers()["authorization"].as_bytes(),
+            expected.authorization
+        );
+        assert_eq!(request.headers()["x-amz-date"], "20150830T123600Z");
+    }
+
+    #[test]
+    fn test_unsigned_payload() {
+        let (client, captured) = client(PayloadMode::Unsigned);
+        let request = client
+            .put("http://localhost:8080/bucket/key")
+            .body("data")
+            .build()
+            .unwrap();
+        block_on(client.execute(request)).unwrap();
+        let request = captured.0.lock().unwrap().take().unwrap();
+        assert_eq!(
+            request.headers()["x-amz-content-sha256"],
+            "UNSIGNED-PAYLOAD"
+        );
+    }
+}
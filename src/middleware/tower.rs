@@ -0,0 +1,243 @@
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::Request;
+use http_body::Body;
+use http_body_util::{BodyExt, Either, Full};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use super::{sign_request, BoxError, PayloadMode, SigningError};
+use crate::nonblocking::{AsyncSigner, BoxFuture};
+
+/// Signs the requests passing through the wrapped service. In the default
+/// `PayloadMode::Signed`, the body is buffered to hash it, so the inner
+/// service gets the buffered body; in `PayloadMode::Unsigned`, it gets the
+/// original body.
+#[derive(Clone)]
+pub struct SigningLayer {
+    signer: Arc<AsyncSigner>,
+    mode: PayloadMode,
+}
+
+impl SigningLayer {
+    pub fn new(signer: Arc<AsyncSigner>) -> SigningLayer {
+        SigningLayer {
+            signer,
+            mode: PayloadMode::default(),
+        }
+    }
+
+    pub fn with_payload_mode(mut self, mode: PayloadMode) -> SigningLayer {
+        self.mode = mode;
+        self
+    }
+}
+
+impl<S> Layer<S> for SigningLayer {
+    type Service = SigningService<S>;
+
+    fn layer(&self, inner: S) -> SigningService<S> {
+        SigningService {
+            inner,
+            signer: self.signer.clone(),
+            mode: self.mode,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SigningService<S> {
+    inner: S,
+    signer: Arc<AsyncSigner>,
+    mode: PayloadMode,
+}
+
+impl<S, B> Service<Request<B>> for SigningService<S>
+where
+    S: Service<Request<Either<Full<Bytes>, B>>> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Into<BoxError>,
+    B: Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<S::Response, BoxError>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        // The clone might not be ready, so call the one that was polled
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let signer = self.signer.clone();
+        let mode = self.mode;
+        Box::pin(async move {
+            let signer = signer.signer().await.map_err(SigningError::from)?;
+            let (mut parts, body) = request.into_parts();
+            let (body, payload) = match mode {
+                PayloadMode::Signed => {
+                    let bytes = body
+                        .collect()
+                        .await
+                        .map_err(|err| SigningError::Body(err.into()))?
+                        .to_bytes();
+                    (Either::Left(Full::new(bytes.clone())), Some(bytes))
+                }
+                PayloadMode::Unsigned => (Either::Right(body), None),
+            };
+
+            let authority = match parts.headers.get(http::header::HOST) {
+                Some(host) => host
+                    .to_str()
+                    .map_err(|_| SigningError::InvalidRequest("invalid host header"))?
+                    .to_owned(),
+                None => match (parts.uri.host(), parts.uri.port_u16()) {
+                    (Some(host), Some(port)) => format!("{}:{}", host, port),
+                    (Some(host), None) => host.to_owned(),
+                    (None, _) => String::new(),
+                },
+            };
+            let headers = sign_request(
+                &signer,
+                &parts.method,
+                &authority,
+                parts.uri.path(),
+                parts.uri.query(),
+                &parts.headers,
+                payload.as_deref(),
+            )?;
+            for (name, value) in headers {
+                parts.headers.insert(name, value);
+            }
+            inner
+                .call(Request::from_parts(parts, body))
+                .await
+                .map_err(Into::into)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nonblocking::block_on;
+    use crate::signer::tests::{test_credentials, test_signer, test_time};
+    use crate::vocab::{AwsRegion, AwsService, Hash, HttpMethod};
+    use std::convert::Infallible;
+    use std::sync::Mutex;
+
+    /// A body of unknown length.
+    struct Chunks(Vec<&'static [u8]>);
+
+    impl Body for Chunks {
+        type Data = Bytes;
+        type Error = Infallible;
+
+        fn poll_frame(
+            mut self: std::pin::Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<http_body::Frame<Bytes>, Infallible>>> {
+            if self.0.is_empty() {
+                return Poll::Ready(None);
+            }
+            let chunk = self.0.remove(0);
+            Poll::Ready(Some(Ok(http_body::Frame::data(Bytes::from_static(chunk)))))
+        }
+    }
+
+    type Captured = Arc<Mutex<Option<Request<Bytes>>>>;
+
+    /// Collects the body of the request it gets, and keeps the request.
+    #[derive(Clone)]
+    struct Capture(Captured);
+
+    impl<B: Body<Data = Bytes> + Send + 'static> Service<Request<B>> for Capture
+    where
+        B::Error: std::fmt::Debug,
+    {
+        type Response = ();
+        type Error = Infallible;
+        type Future = BoxFuture<'static, Result<(), Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<B>) -> Self::Future {
+            let captured = self.0.clone();
+            Box::pin(async move {
+                let (parts, body) = request.into_parts();
+                let body = body.collect().await.unwrap().to_bytes();
+                *captured.lock().unwrap() = Some(Request::from_parts(parts, body));
+                Ok(())
+            })
+        }
+    }
+
+    fn service(mode: PayloadMode) -> (SigningService<Capture>, Captured) {
+        let signer = AsyncSigner::new(
+            Arc::new(test_credentials()),
+            AwsRegion::UsEast1,
+            AwsService::S3,
+        )
+        .with_time_source(test_time());
+        let captured = Captured::default();
+        let layer = SigningLayer::new(Arc::new(signer)).with_payload_mode(mode);
+        (layer.layer(Capture(captured.clone())), captured)
+    }
+
+    #[test]
+    fn test_signs_buffered_body() {
+        let (mut service, captured) = service(PayloadMode::Signed);
+        let request = Request::put("https://examplebucket.s3.amazonaws.com/test%20file.txt")
+            .header("content-type", "text/plain")
+            .body(Full::new(Bytes::from_static(b"Welcome to Amazon S3.")))
+            .unwrap();
+        block_on(service.call(request)).unwrap();
+        let request = captured.lock().unwrap().take().unwrap();
+        assert_eq!(&request.body()[..], b"Welcome to Amazon S3.");
+
+        let signer = test_signer(AwsRegion::UsEast1, AwsService::S3);
+        let hash = Hash::new(b"Welcome to Amazon S3.");
+        let mut buffer = Vec::new();
+        let expected = signer.sign(
+            &mut buffer,
+            HttpMethod::Put,
+            b"/test file.txt",
+            &[],
+            &[
+                (b"content-type", b"text/plain"),
+                (b"host", b"examplebucket.s3.amazonaws.com"),
+                (b"x-amz-content-sha256", hash.as_hex()),
+            ],
+            &hash,
+        );
+        let header = |name| request.headers()[name].as_bytes();
+        assert_eq!(header("authorization"), expected.authorization);
+        assert_eq!(header("x-amz-date"), b"20150830T123600Z");
+        assert_eq!(header("x-amz-content-sha256"), hash.as_hex());
+    }
+
+    #[test]
+    fn test_streams_unsigned_body() {
+        let (mut service, captured) = service(PayloadMode::Unsigned);
+        let body = Chunks(vec![&b"stream"[..], b"ed"]);
+        let request = Request::post("http://localhost:9000/bucket/upload?uploads")
+            .body(body)
+            .unwrap();
+        block_on(service.call(request)).unwrap();
+        let request = captured.lock().unwrap().take().unwrap();
+        assert_eq!(&request.body()[..], b"streamed");
+        assert_eq!(
+            request.headers()["x-amz-content-sha256"].as_bytes(),
+            b"UNSIGNED-PAYLOAD"
+        );
+        let authorization = request.headers()["authorization"].to_str().unwrap();
+        assert!(authorization.contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date,"));
+    }
+}
@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use crate::credentials::Credentials;
+use crate::signer::Signer;
+use crate::util::{base64, json, url_encode, VecExt};
+use crate::vocab::{AwsRegion, AwsService, Hash, HttpMethod};
+
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+const DEFAULT_EXPIRES_IN: Duration = Duration::from_secs(15 * 60);
+
+/// The payload of the `AWS_MSK_IAM` SASL mechanism, which authenticates a
+/// Kafka client to an MSK broker: the fields of a presigned
+/// `kafka-cluster:Connect` request to the broker, as JSON.
+#[derive(Clone, Debug)]
+pub struct MskAuthPayload {
+    host: String,
+    user_agent: String,
+    expires_in: Duration,
+}
+
+impl MskAuthPayload {
+    /// For the broker at `host`, without the port.
+    pub fn new(host: impl Into<String>) -> MskAuthPayload {
+        MskAuthPayload {
+            host: host.into(),
+            user_agent: DEFAULT_USER_AGENT.to_owned(),
+            expires_in: DEFAULT_EXPIRES_IN,
+        }
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> MskAuthPayload {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Sets how long the payload can be used to authenticate. The default is
+    /// 15 minutes.
+    pub fn with_expires_in(mut self, expires_in: Duration) -> MskAuthPayload {
+        self.expires_in = expires_in;
+        self
+    }
+
+    /// Signs the payload at the current time of the signer's clock. The
+    /// signer needs to be for `AwsService::KafkaCluster` in the cluster's
+    /// region. Returns the JSON to send as the SASL authentication bytes.
+    pub fn sign(&self, signer: &Signer) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        let query = presign_connect(&mut buffer, signer, &self.host, self.expires_in);
+
+        let mut payload = Vec::new();
+        payload.extend_from_slice(b"{\"version\":\"2020_10_22\",\"host\":");
+        json::write_string(&mut payload, &self.host);
+        payload.extend_from_slice(b",\"user-agent\":");
+        json::write_string(&mut payload, &self.user_agent);
+        payload.extend_from_slice(b",\"action\":\"kafka-cluster:Connect\"");
+        for param in query.split(|&byte| byte == b'&') {
+            let (key, val) = match param.iter().position(|&byte| byte == b'=') {
+                Some(i) => (&param[..i], &param[i + 1..]),
+                None => (param, &[][..]),
+            };
+            if key == b"Action" {
+                continue;
+            }
+            let mut decoded = Vec::new();
+            url_encode::decode(&mut decoded, val);
+            payload.push(b',');
+            let mut key_lowercase = Vec::new();
+            key_lowercase.push_lowercase(key);
+            json::write_string(
+                &mut payload,
+                std::str::from_utf8(&key_lowercase).expect("always ASCII"),
+            );
+            payload.push(b':');
+            json::write_string(&mut payload, &String::from_utf8_lossy(&decoded));
+        }
+        payload.push(b'}');
+        payload
+    }
+}
+
+/// Generates a token for the `OAUTHBEARER` SASL mechanism of MSK, which
+/// authenticates a Kafka client to any broker of the clusters in `region`.
+/// It's valid for 15 minutes.
+pub fn msk_oauth_token(region: AwsRegion, credentials: Credentials) -> String {
+    let signer = Signer::from_credentials(credentials, region, AwsService::KafkaCluster);
+    oauth_token(&signer, region, DEFAULT_USER_AGENT)
+}
+
+/// The token is a presigned `kafka-cluster:Connect` URL for the regional
+/// endpoint, with the user agent added after signing.
+fn oauth_token(signer: &Signer, region: AwsRegion, user_agent: &str) -> String {
+    let host = format!("kafka.{}.amazonaws.com", region.to_str());
+    let mut buffer = Vec::new();
+    let query = presign_connect(&mut buffer, signer, &host, DEFAULT_EXPIRES_IN);
+    let mut url = [b"https://", host.as_bytes(), b"/?", query].concat();
+    url.extend_from_slice(b"&User-Agent=");
+    url.push_url_encoded(user_agent.as_bytes());
+    let mut token = Vec::new();
+    base64::encode_url(&mut token, &url);
+    String::from_utf8(token).expect("always ASCII")
+}
+
+fn presign_connect<'a>(
+    buffer: &'a mut Vec<u8>,
+    signer: &Signer,
+    host: &str,
+    expires_in: Duration,
+) -> &'a [u8] {
+    signer.presign(
+        buffer,
+        HttpMethod::Get,
+        host.as_bytes(),
+        b"/",
+        &[(b"Action", b"kafka-cluster:Connect")],
+        expires_in,
+        &Hash::new(b""),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::SessionToken;
+    use crate::signer::tests::test_signer;
+    use crate::util::json::Json;
+
+    const HOST: &str = "b-1.mycluster.abc123.c2.kafka.us-east-1.amazonaws.com";
+
+    fn signer() -> Signer {
+        test_signer(AwsRegion::UsEast1, AwsService::KafkaCluster)
+    }
+
+    #[test]
+    fn test_auth_payload() {
+        let signer = signer().with_session_token(SessionToken::new(b"token/+=".to_vec()));
+        let payload = MskAuthPayload::new(HOST)
+            .with_user_agent("my-client/1.0")
+            .with_expires_in(Duration::from_secs(300))
+            .sign(&signer);
+        let json = Json::parse(&payload).unwrap();
+        let field = |key| json.get(key).and_then(Json::as_str);
+        assert_eq!(field("version"), Some("2020_10_22"));
+        assert_eq!(field("host"), Some(HOST));
+        assert_eq!(field("user-agent"), Some("my-client/1.0"));
+        assert_eq!(field("action"), Some("kafka-cluster:Connect"));
+        assert_eq!(field("x-amz-algorithm"), Some("AWS4-HMAC-SHA256"));
+        assert_eq!(
+            field("x-amz-credential"),
+            Some("AKIDEXAMPLE000000000/20150830/us-east-1/kafka-cluster/aws4_request")
+        );
+        assert_eq!(field("x-amz-date"), Some("20150830T123600Z"));
+        assert_eq!(field("x-amz-expires"), Some("300"));
+        assert_eq!(field("x-amz-signedheaders"), Some("host"));
+        assert_eq!(field("x-amz-security-token"), Some("token/+="));
+        // From botocore's SigV4QueryAuth for the Connect request to the
+        // broker, with the same credentials, token and time
+        assert_eq!(
+            field("x-amz-signature"),
+            Some("0feb15b3dac45301b1fdbe2feff8e486568eb1718b4631b6e224b403d50c91ab")
+        );
+    }
+
+    #[test]
+    fn test_oauth_token() {
+        // As generated by aws-msk-iam-sasl-signer-python, which appends the
+        // user agent to the URL presigned by botocore
+        assert_eq!(
+            oauth_token(&signer(), AwsRegion::UsEast1, "my-client/1.0"),
+            "aHR0cHM6Ly9rYWZrYS51cy1lYXN0LTEuYW1hem9uYXdzLmNvbS8_QWN0aW9uPWthZmthLWNsdXN0ZXIlM0FDb25uZWN0JlgtQW16LUFsZ29yaXRobT1BV1M0LUhNQUMtU0hBMjU2JlgtQW16LUNyZWRlbnRpYWw9QUtJREVYQU1QTEUwMDAwMDAwMDAlMkYyMDE1MDgzMCUyRnVzLWVhc3QtMSUyRmthZmthLWNsdXN0ZXIlMkZhd3M0X3JlcXVlc3QmWC1BbXotRGF0ZT0yMDE1MDgzMFQxMjM2MDBaJlgtQW16LUV4cGlyZXM9OTAwJlgtQW16LVNpZ25lZEhlYWRlcnM9aG9zdCZYLUFtei1TaWduYXR1cmU9YWUxOTU4Y2VlODc2MTQ3NmFiODU4YTBlMjU0YmJlZGRjODc5Y2U3MGY2NmVjM2IxNmUyYTBkNmE0OGNkYzQwNyZVc2VyLUFnZW50PW15LWNsaWVudCUyRjEuMA"
+        );
+    }
+}
@@ -0,0 +1,704 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use crate::keys::signing_key;
+use crate::secret::SecretAccessKey;
+use crate::signer::Signer;
+use crate::util::base64;
+use crate::util::iso8601::{BASIC_FORMAT_SEC_UTC, EXTENDED_FORMAT_MILLIS_UTC};
+use crate::util::json::{write_string, Json};
+use crate::util::{FormatTime, ParseTime, VecExt};
+use crate::vocab::{AwsRegion, AwsService, Signature};
+
+/// A condition on the fields of a POST upload. Field names are given
+/// without the leading `$`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Condition {
+    /// The field must have exactly this value.
+    Eq(String, String),
+    /// The field must start with this prefix. An empty prefix allows any
+    /// value.
+    StartsWith(String, String),
+    /// The size of the uploaded file must be within these bounds, in bytes
+    /// and inclusive.
+    ContentLengthRange(u64, u64),
+}
+
+/// A policy for uploading to S3 with an HTML form, straight from the
+/// browser. The form is posted to the bucket's endpoint with the fields
+/// returned by `sign`, the fields the conditions are about, like `key`, and
+/// lastly the `file` field.
+#[derive(Clone, Debug)]
+pub struct PostPolicy {
+    expires_in: Duration,
+    conditions: Vec<Condition>,
+}
+
+impl PostPolicy {
+    /// The policy is valid for `expires_in` from when it's signed.
+    pub fn new(bucket: impl Into<String>, expires_in: Duration) -> PostPolicy {
+        PostPolicy {
+            expires_in,
+            conditions: vec![Condition::Eq("bucket".into(), bucket.into())],
+        }
+    }
+
+    pub fn with_key(self, key: impl Into<String>) -> PostPolicy {
+        self.with_condition(Condition::Eq("key".into(), key.into()))
+    }
+
+    /// Allows any key with this prefix, like `uploads/`. The form can then
+    /// use `${filename}` in the `key` field for the name of the user's file.
+    pub fn with_key_prefix(self, prefix: impl Into<String>) -> PostPolicy {
+        self.with_condition(Condition::StartsWith("key".into(), prefix.into()))
+    }
+
+    pub fn with_content_length_range(self, min: u64, max: u64) -> PostPolicy {
+        self.with_condition(Condition::ContentLengthRange(min, max))
+    }
+
+    pub fn with_content_type(self, content_type: impl Into<String>) -> PostPolicy {
+        self.with_condition(Condition::Eq("Content-Type".into(), content_type.into()))
+    }
+
+    /// Allows content types with this prefix, like `image/`.
+    pub fn with_content_type_prefix(self, prefix: impl Into<String>) -> PostPolicy {
+        self.with_condition(Condition::StartsWith("Content-Type".into(), prefix.into()))
+    }
+
+    /// Adds a condition on any other field, like `acl` or
+    /// `success_action_redirect`.
+    pub fn with_condition(mut self, condition: Condition) -> PostPolicy {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Signs the policy at the current time of the signer's clock. The
+    /// signer needs to be for S3 in the bucket's region. Returns the
+    /// `x-amz-algorithm`, `x-amz-credential`, `x-amz-date`, and, with
+    /// temporary credentials, `x-amz-security-token` form fields, followed
+    /// by `policy` and `x-amz-signature`.
+    pub fn sign(&self, signer: &Signer) -> Vec<(&'static str, String)> {
+        let now = signer.clock().now();
+        let mut x_amz_date = BASIC_FORMAT_SEC_UTC;
+        now.write_iso8602_basic_seconds_utc(&mut x_amz_date);
+        let mut expiration = EXTENDED_FORMAT_MILLIS_UTC;
+        (now + self.expires_in).write_iso8601_extended_millis_utc(&mut expiration);
+        let mut credential = Vec::new();
+        signer.write_credential(&mut credential, now);
+        let ascii = |bytes: Vec<u8>| String::from_utf8(bytes).expect("always ASCII");
+
+        let mut fields = vec![
+            ("x-amz-algorithm", "AWS4-HMAC-SHA256".to_owned()),
+            ("x-amz-credential", ascii(credential)),
+            ("x-amz-date", ascii(x_amz_date.to_vec())),
+        ];
+        if let Some(token) = signer.session_token() {
+            let token = String::from_utf8_lossy(token.as_bytes()).into_owned();
+            fields.push(("x-amz-security-token", token));
+        }
+
+        let mut document = Vec::new();
+        document.extend_from_slice(b"{\"expiration\":\"");
+        document.extend_from_slice(&expiration);
+        document.extend_from_slice(b"\",\"conditions\":[");
+        for condition in &self.conditions {
+            write_condition(&mut document, condition);
+            document.push(b',');
+        }
+        for (name, value) in &fields {
+            write_condition(&mut document, &Condition::Eq((*name).into(), value.clone()));
+            document.push(b',');
+        }
+        let _ = document.pop(); // Popping off the last ','
+        document.extend_from_slice(b"]}");
+
+        let mut policy = Vec::new();
+        base64::encode(&mut policy, &document);
+        let signature = signer.signing_key_at(now).sign(&policy);
+        let mut signature_hex = Vec::new();
+        signature.write_hex(signature_hex.space_for_sha256());
+
+        fields.push(("policy", ascii(policy)));
+        fields.push(("x-amz-signature", ascii(signature_hex)));
+        fields
+    }
+}
+
+fn write_condition(buffer: &mut Vec<u8>, condition: &Condition) {
+    match condition {
+        Condition::Eq(field, value) => {
+            buffer.push(b'{');
+            write_string(buffer, field);
+            buffer.push(b':');
+            write_string(buffer, value);
+            buffer.push(b'}');
+        }
+        Condition::StartsWith(field, prefix) => {
+            buffer.extend_from_slice(b"[\"starts-with\",");
+            write_string(buffer, &format!("${}", field));
+            buffer.push(b',');
+            write_string(buffer, prefix);
+            buffer.push(b']');
+        }
+        Condition::ContentLengthRange(min, max) => {
+            let range = format!("[\"content-length-range\",{},{}]", min, max);
+            buffer.extend_from_slice(range.as_bytes());
+        }
+    }
+}
+
+/// Why a POST upload was rejected.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PostPolicyError {
+    /// The body isn't a well-formed `multipart/form-data` form.
+    MalformedForm(&'static str),
+    MissingField(&'static str),
+    InvalidField(&'static str),
+    SignatureMismatch,
+    /// The signature is right, but the policy can't be understood.
+    MalformedPolicy(&'static str),
+    Expired,
+    /// A condition of the policy doesn't hold for the upload.
+    ConditionFailed(Condition),
+    /// A field of the form isn't covered by any condition of the policy.
+    UnexpectedField(String),
+}
+
+impl fmt::Display for PostPolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use PostPolicyError::*;
+        match self {
+            MalformedForm(reason) => write!(f, "malformed form: {}", reason),
+            MissingField(name) => write!(f, "missing form field {}", name),
+            InvalidField(name) => write!(f, "invalid form field {}", name),
+            SignatureMismatch => f.write_str("the policy signature doesn't match"),
+            MalformedPolicy(reason) => write!(f, "malformed policy: {}", reason),
+            Expired => f.write_str("the policy has expired"),
+            ConditionFailed(Condition::Eq(field, value)) => {
+                write!(f, "policy condition failed: {} must be {:?}", field, value)
+            }
+            ConditionFailed(Condition::StartsWith(field, prefix)) => write!(
+                f,
+                "policy condition failed: {} must start with {:?}",
+                field, prefix
+            ),
+            ConditionFailed(Condition::ContentLengthRange(min, max)) => write!(
+                f,
+                "policy condition failed: the file must be {} to {} bytes",
+                min, max
+            ),
+            UnexpectedField(name) => write!(f, "form field {} isn't allowed by the policy", name),
+        }
+    }
+}
+
+impl std::error::Error for PostPolicyError {}
+
+/// The fields of a POST upload form, for checking them against the policy
+/// on the receiving end.
+#[derive(Debug)]
+pub struct PostForm<'a> {
+    /// With lowercase names, as field names aren't case-sensitive
+    fields: Vec<(String, &'a [u8])>,
+    file: &'a [u8],
+    filename: Option<&'a [u8]>,
+}
+
+impl<'a> PostForm<'a> {
+    /// Parses a `multipart/form-data` body, given the `Content-Type` header
+    /// with the boundary. Like with S3, the `file` field needs to come last;
+    /// anything after it is ignored.
+    pub fn parse(content_type: &[u8], body: &'a [u8]) -> Result<PostForm<'a>, PostPolicyError> {
+        let boundary = header_param(content_type, b"boundary")
+            .filter(|boundary| !boundary.is_empty())
+            .ok_or(PostPolicyError::MalformedForm("no boundary"))?;
+        let mut delimiter = b"\r\n--".to_vec();
+        delimiter.extend_from_slice(boundary);
+
+        // The first delimiter may come after a preamble
+        let first =
+            find(body, &delimiter[2..]).ok_or(PostPolicyError::MalformedForm("no parts"))?;
+        let mut rest = &body[first + delimiter.len() - 2..];
+        let mut fields = Vec::new();
+        loop {
+            if rest.starts_with(b"--") {
+                return Err(PostPolicyError::MissingField("file"));
+            }
+            rest = rest
+                .strip_prefix(b"\r\n")
+                .ok_or(PostPolicyError::MalformedForm("malformed delimiter"))?;
+            let head_len = if rest.starts_with(b"\r\n") {
+                0
+            } else {
+                find(rest, b"\r\n\r\n")
+                    .ok_or(PostPolicyError::MalformedForm("unterminated headers"))?
+                    + 2
+            };
+            let part_len = find(rest, &delimiter)
+                .ok_or(PostPolicyError::MalformedForm("unterminated part"))?;
+            if part_len < head_len + 2 {
+                return Err(PostPolicyError::MalformedForm("unterminated headers"));
+            }
+            let disposition = rest[..head_len]
+                .split(|&b| b == b'\n')
+                .filter_map(|line| {
+                    let colon = line.iter().position(|&b| b == b':')?;
+                    let (name, value) = line.split_at(colon);
+                    Some(value[1..].trim_ascii()).filter(|_| {
+                        name.trim_ascii()
+                            .eq_ignore_ascii_case(b"content-disposition")
+                    })
+                })
+                .next()
+                .ok_or(PostPolicyError::MalformedForm(
+                    "part without content-disposition",
+                ))?;
+            let name = header_param(disposition, b"name")
+                .ok_or(PostPolicyError::MalformedForm("part without name"))?;
+            let content = &rest[head_len + 2..part_len];
+            rest = &rest[part_len + delimiter.len()..];
+
+            if name.eq_ignore_ascii_case(b"file") {
+                return Ok(PostForm {
+                    fields,
+                    file: content,
+                    filename: header_param(disposition, b"filename"),
+                });
+            }
+            let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+            fields.push((name, content));
+        }
+    }
+
+    /// Looks up a field by its name, ignoring case.
+    pub fn field(&self, name: &str) -> Option<&'a [u8]> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+
+    pub fn file(&self) -> &'a [u8] {
+        self.file
+    }
+
+    /// The key to store the file under, with `${filename}` replaced by the
+    /// name of the uploaded file.
+    pub fn key(&self) -> Option<Vec<u8>> {
+        let key = self.field("key")?;
+        let filename = self.filename.unwrap_or(b"");
+        let mut output = Vec::with_capacity(key.len());
+        let mut rest = key;
+        while let Some(i) = find(rest, b"${filename}") {
+            output.extend_from_slice(&rest[..i]);
+            output.extend_from_slice(filename);
+            rest = &rest[i + b"${filename}".len()..];
+        }
+        output.extend_from_slice(rest);
+        Some(output)
+    }
+
+    /// The access key ID of `x-amz-credential`, for looking up the secret
+    /// to `verify` with.
+    pub fn key_id(&self) -> Option<&'a [u8]> {
+        self.field("x-amz-credential")?.split(|&b| b == b'/').next()
+    }
+
+    /// Checks that the policy is signed with the secret access key, that
+    /// it hasn't expired at `now`, that the upload to `bucket` satisfies all
+    /// of its conditions, and that all of the fields are covered by them.
+    pub fn verify(
+        &self,
+        bucket: &str,
+        secret_access_key: &SecretAccessKey,
+        now: SystemTime,
+    ) -> Result<(), PostPolicyError> {
+        use PostPolicyError::*;
+        let field = |name: &'static str| self.field(name).ok_or(MissingField(name));
+
+        if field("x-amz-algorithm")? != b"AWS4-HMAC-SHA256" {
+            return Err(InvalidField("x-amz-algorithm"));
+        }
+        let credential: Vec<&[u8]> = field("x-amz-credential")?.split(|&b| b == b'/').collect();
+        let (date, region) = match credential[..] {
+            [_, date, region, b"s3", b"aws4_request"] => (date, region),
+            _ => return Err(InvalidField("x-amz-credential")),
+        };
+        let region = AwsRegion::try_from(&region).map_err(|_| InvalidField("x-amz-credential"))?;
+        let x_amz_date = field("x-amz-date")?;
+        let request_time = SystemTime::parse_iso8601_basic_seconds_utc(x_amz_date)
+            .map_err(|_| InvalidField("x-amz-date"))?;
+        if &x_amz_date[..8] != date {
+            return Err(InvalidField("x-amz-credential"));
+        }
+        let signature = Signature::try_from(field("x-amz-signature")?)
+            .map_err(|_| InvalidField("x-amz-signature"))?;
+        let policy = field("policy")?;
+        let key = signing_key(secret_access_key, request_time, region, AwsService::S3);
+        if !key.verify(policy, signature.as_bytes()) {
+            return Err(SignatureMismatch);
+        }
+
+        let mut document = Vec::new();
+        base64::decode(&mut document, policy).map_err(|_| MalformedPolicy("not base64"))?;
+        let document = Json::parse(&document).map_err(|_| MalformedPolicy("not JSON"))?;
+        let expiration = document
+            .get("expiration")
+            .and_then(Json::as_str)
+            .ok_or(MalformedPolicy("no expiration"))?;
+        let expiration = SystemTime::parse_iso8601_extended(expiration.as_bytes())
+            .map_err(|_| MalformedPolicy("invalid expiration"))?;
+        if now >= expiration {
+            return Err(Expired);
+        }
+        let conditions = match document.get("conditions") {
+            Some(Json::Array(conditions)) => conditions,
+            _ => return Err(MalformedPolicy("no conditions")),
+        };
+
+        let mut covered = Vec::new();
+        for condition in conditions {
+            let condition = parse_condition(condition)?;
+            self.check(&condition, bucket)?;
+            if let Condition::Eq(field, _) | Condition::StartsWith(field, _) = condition {
+                covered.push(field);
+            }
+        }
+        let exempt = |name: &str| {
+            name == "policy" || name == "x-amz-signature" || name.starts_with("x-ignore-")
+        };
+        match self.fields.iter().find(|(name, _)| {
+            !exempt(name) && !covered.iter().any(|field| field.eq_ignore_ascii_case(name))
+        }) {
+            Some((name, _)) => Err(UnexpectedField(name.clone())),
+            None => Ok(()),
+        }
+    }
+
+    fn check(&self, condition: &Condition, bucket: &str) -> Result<(), PostPolicyError> {
+        let value = |field: &str| match &*field.to_ascii_lowercase() {
+            "bucket" => Some(bucket.as_bytes().to_vec()),
+            "key" => self.key(),
+            field => self.field(field).map(<[u8]>::to_vec),
+        };
+        let holds = match condition {
+            Condition::Eq(field, expected) => value(field).as_deref() == Some(expected.as_bytes()),
+            Condition::StartsWith(field, prefix) => value(field)
+                .unwrap_or_default()
+                .starts_with(prefix.as_bytes()),
+            Condition::ContentLengthRange(min, max) => {
+                (*min..=*max).contains(&(self.file.len() as u64))
+            }
+        };
+        if holds {
+            Ok(())
+        } else {
+            Err(PostPolicyError::ConditionFailed(condition.clone()))
+        }
+    }
+}
+
+/// Reads a condition in either of its forms: `{"field": "value"}`, or an
+/// array of the operator and its operands.
+fn parse_condition(condition: &Json) -> Result<Condition, PostPolicyError> {
+    let invalid = PostPolicyError::MalformedPolicy("invalid condition");
+    let field = |name: &Json| {
+        name.as_str()
+            .map(|name| name.strip_prefix('$').unwrap_or(name).to_owned())
+    };
+    let bound = |bound: &Json| match bound {
+        Json::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as u64),
+        Json::String(s) => s.parse().ok(),
+        _ => None,
+    };
+    match condition {
+        Json::Object(members) => match &members[..] {
+            [(field, Json::String(value))] => Ok(Condition::Eq(field.clone(), value.clone())),
+            _ => Err(invalid),
+        },
+        Json::Array(operands) => match &operands[..] {
+            [Json::String(op), name, Json::String(value)] if op.eq_ignore_ascii_case("eq") => {
+                Ok(Condition::Eq(field(name).ok_or(invalid)?, value.clone()))
+            }
+            [Json::String(op), name, Json::String(prefix)]
+                if op.eq_ignore_ascii_case("starts-with") =>
+            {
+                Ok(Condition::StartsWith(
+                    field(name).ok_or(invalid)?,
+                    prefix.clone(),
+                ))
+            }
+            [Json::String(op), min, max] if op.eq_ignore_ascii_case("content-length-range") => {
+                match (bound(min), bound(max)) {
+                    (Some(min), Some(max)) => Ok(Condition::ContentLengthRange(min, max)),
+                    _ => Err(invalid),
+                }
+            }
+            _ => Err(invalid),
+        },
+        _ => Err(invalid),
+    }
+}
+
+/// Gets a parameter of a header value like `form-data; name="key"`.
+fn header_param<'a>(value: &'a [u8], param: &[u8]) -> Option<&'a [u8]> {
+    let mut rest = value;
+    while !rest.is_empty() {
+        let name_len = rest
+            .iter()
+            .position(|&b| b == b'=' || b == b';')
+            .unwrap_or(rest.len());
+        let name = rest[..name_len].trim_ascii();
+        rest = &rest[name_len..];
+        if rest.first() != Some(&b'=') {
+            rest = rest.get(1..).unwrap_or(b"");
+            continue;
+        }
+        rest = rest[1..].trim_ascii_start();
+        let param_value = if rest.first() == Some(&b'"') {
+            let len = rest[1..].iter().position(|&b| b == b'"')?;
+            let quoted = &rest[1..1 + len];
+            rest = &rest[len + 2..];
+            quoted
+        } else {
+            let len = rest.iter().position(|&b| b == b';').unwrap_or(rest.len());
+            let token = rest[..len].trim_ascii();
+            rest = &rest[len..];
+            token
+        };
+        if name.eq_ignore_ascii_case(param) {
+            return Some(param_value);
+        }
+        rest = rest
+            .iter()
+            .position(|&b| b == b';')
+            .map_or(&b""[..], |i| &rest[i + 1..]);
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{signing_key, validate_secret_key};
+    use crate::secret::SessionToken;
+    use crate::signer::tests::{test_signer, KEY_ID, REQUEST_TIME, SECRET};
+    use crate::vocab::{AwsRegion, AwsService};
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_sign() {
+        let signer = test_signer(AwsRegion::EuWest1, AwsService::S3)
+            .with_session_token(SessionToken::new(&b"FwoGZXIvYXdzEJr"[..]));
+        let fields = PostPolicy::new("examplebucket", Duration::from_secs(3600))
+            .with_key_prefix("user/eric/")
+            .with_content_length_range(1, 10485760)
+            .with_content_type_prefix("image/")
+            .with_condition(Condition::Eq("acl".into(), "public-read".into()))
+            .sign(&signer);
+
+        let names: Vec<_> = fields.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            [
+                "x-amz-algorithm",
+                "x-amz-credential",
+                "x-amz-date",
+                "x-amz-security-token",
+                "policy",
+                "x-amz-signature"
+            ]
+        );
+        assert_eq!(
+            fields[1].1,
+            "AKIDEXAMPLE000000000/20150830/eu-west-1/s3/aws4_request"
+        );
+        assert_eq!(fields[2].1, "20150830T123600Z");
+
+        let document = concat!(
+            r#"{"expiration":"2015-08-30T13:36:00.000Z","conditions":["#,
+            r#"{"bucket":"examplebucket"},["starts-with","$key","user/eric/"],"#,
+            r#"["content-length-range",1,10485760],["starts-with","$Content-Type","image/"],"#,
+            r#"{"acl":"public-read"},{"x-amz-algorithm":"AWS4-HMAC-SHA256"},"#,
+            r#"{"x-amz-credential":"AKIDEXAMPLE000000000/20150830/eu-west-1/s3/aws4_request"},"#,
+            r#"{"x-amz-date":"20150830T123600Z"},{"x-amz-security-token":"FwoGZXIvYXdzEJr"}]}"#,
+        );
+        let mut policy = Vec::new();
+        base64::encode(&mut policy, document.as_bytes());
+        assert_eq!(fields[4].1.as_bytes(), &policy[..]);
+
+        let key = signing_key(
+            &validate_secret_key(SECRET).unwrap(),
+            UNIX_EPOCH + Duration::from_secs(REQUEST_TIME),
+            AwsRegion::EuWest1,
+            AwsService::S3,
+        );
+        let mut signature = [0; 64];
+        key.sign(&policy).write_hex(&mut signature);
+        assert_eq!(fields[5].1.as_bytes(), &signature[..]);
+    }
+
+    /// Builds the body of a form the way browsers do.
+    fn form(fields: &[(&str, &str)], filename: &str, file: &[u8]) -> Vec<u8> {
+        let mut body = b"preamble\r\n".to_vec();
+        for (name, value) in fields {
+            body.extend_from_slice(b"--AaB03x\r\n");
+            let disposition = format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name);
+            body.extend_from_slice(disposition.as_bytes());
+            body.extend_from_slice(value.as_bytes());
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(b"--AaB03x\r\n");
+        let disposition = format!(
+            "content-disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: image/png\r\n\r\n",
+            filename
+        );
+        body.extend_from_slice(disposition.as_bytes());
+        body.extend_from_slice(file);
+        body.extend_from_slice(b"\r\n--AaB03x--\r\n");
+        body
+    }
+
+    fn verify(fields: &[(&str, &str)], file: &[u8], now: u64) -> Result<(), PostPolicyError> {
+        let body = form(fields, "cat; 1.png", file);
+        let form = PostForm::parse(b"multipart/form-data; boundary=\"AaB03x\"", &body)?;
+        assert_eq!(form.key_id(), Some(&KEY_ID[..]));
+        let secret = validate_secret_key(SECRET).unwrap();
+        form.verify(
+            "examplebucket",
+            &secret,
+            UNIX_EPOCH + Duration::from_secs(now),
+        )
+    }
+
+    #[test]
+    fn test_verify() {
+        let signed = PostPolicy::new("examplebucket", Duration::from_secs(600))
+            .with_key_prefix("user/eric/")
+            .with_content_length_range(1, 8)
+            .with_content_type("image/png")
+            .sign(&test_signer(AwsRegion::UsEast1, AwsService::S3));
+        let mut fields = vec![
+            ("key", "user/eric/${filename}"),
+            ("Content-Type", "image/png"),
+        ];
+        fields.extend(signed.iter().map(|(name, value)| (*name, &value[..])));
+        assert_eq!(verify(&fields, b"\x89PNG", REQUEST_TIME), Ok(()));
+
+        let body = form(&fields, "cat; 1.png", b"\x89PNG");
+        let form = PostForm::parse(b"multipart/form-data; boundary=AaB03x", &body).unwrap();
+        assert_eq!(form.key().unwrap(), b"user/eric/cat; 1.png");
+        assert_eq!(form.file(), b"\x89PNG");
+        assert_eq!(form.field("content-type"), Some(&b"image/png"[..]));
+
+        assert_eq!(
+            verify(&fields, b"\x89PNG", REQUEST_TIME + 600),
+            Err(PostPolicyError::Expired)
+        );
+        assert_eq!(
+            verify(&fields, b"\x89PNG\r\n\x1a\n\0", REQUEST_TIME),
+            Err(PostPolicyError::ConditionFailed(
+                Condition::ContentLengthRange(1, 8)
+            ))
+        );
+
+        let mut other_key = fields.clone();
+        other_key[0].1 = "user/alice/${filename}";
+        assert_eq!(
+            verify(&other_key, b"\x89PNG", REQUEST_TIME),
+            Err(PostPolicyError::ConditionFailed(Condition::StartsWith(
+                "key".into(),
+                "user/eric/".into()
+            )))
+        );
+
+        let mut extra = fields.clone();
+        extra.push(("acl", "public-read"));
+        assert_eq!(
+            verify(&extra, b"\x89PNG", REQUEST_TIME),
+            Err(PostPolicyError::UnexpectedField("acl".into()))
+        );
+        extra[fields.len()] = ("x-ignore-tracking", "1");
+        assert_eq!(verify(&extra, b"\x89PNG", REQUEST_TIME), Ok(()));
+
+        let mut tampered = fields.clone();
+        let policy = tampered
+            .iter_mut()
+            .find(|(name, _)| *name == "policy")
+            .unwrap();
+        let mut document = Vec::new();
+        base64::decode(&mut document, policy.1.as_bytes()).unwrap();
+        let document = String::from_utf8(document)
+            .unwrap()
+            .replace("user/eric/", "user/");
+        let mut encoded = Vec::new();
+        base64::encode(&mut encoded, document.as_bytes());
+        let encoded = String::from_utf8(encoded).unwrap();
+        policy.1 = &encoded;
+        assert_eq!(
+            verify(&tampered, b"\x89PNG", REQUEST_TIME),
+            Err(PostPolicyError::SignatureMismatch)
+        );
+
+        let missing: Vec<_> = fields
+            .iter()
+            .filter(|(name, _)| *name != "x-amz-date")
+            .cloned()
+            .collect();
+        assert_eq!(
+            verify(&missing, b"\x89PNG", REQUEST_TIME),
+            Err(PostPolicyError::MissingField("x-amz-date"))
+        );
+    }
+
+    #[test]
+    fn test_parse_condition() {
+        let parse = |json: &str| parse_condition(&Json::parse(json.as_bytes()).unwrap());
+        assert_eq!(
+            parse(r#"["eq", "$acl", "private"]"#),
+            Ok(Condition::Eq("acl".into(), "private".into()))
+        );
+        assert_eq!(
+            parse(r#"["content-length-range", "0", 1048576]"#),
+            Ok(Condition::ContentLengthRange(0, 1048576))
+        );
+        assert!(parse(r#"["content-length-range", -1, 10]"#).is_err());
+        assert!(parse(r#"{"acl": "private", "key": "a"}"#).is_err());
+        assert!(parse(r#"["ends-with", "$key", ".png"]"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_malformed_form() {
+        let parse = |body: &[u8]| {
+            PostForm::parse(b"multipart/form-data; boundary=AaB03x", body).map(|_| ())
+        };
+        assert_eq!(
+            PostForm::parse(b"multipart/form-data", b"").map(|_| ()),
+            Err(PostPolicyError::MalformedForm("no boundary"))
+        );
+        assert_eq!(
+            parse(
+                b"--AaB03x\r\nContent-Disposition: form-data; name=\"key\"\r\n\r\na\r\n--AaB03x--"
+            ),
+            Err(PostPolicyError::MissingField("file"))
+        );
+        assert_eq!(
+            parse(b"--AaB03x\r\nContent-Disposition: form-data; name=\"file\"\r\n\r\nabc"),
+            Err(PostPolicyError::MalformedForm("unterminated part"))
+        );
+        assert_eq!(
+            parse(b"--AaB03x\r\nContent-Type: text/plain\r\n\r\na\r\n--AaB03x--"),
+            Err(PostPolicyError::MalformedForm(
+                "part without content-disposition"
+            ))
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use std::time::Duration;
+
+use crate::c_request::ensure_query_order;
+use crate::signer::{SignedRequest, Signer};
+use crate::util::VecExt;
+use crate::vocab::{AwsRegion, Hash, HttpMethod};
+
+mod multipart;
+
+pub use self::multipart::{MultipartUpload, PartNumberError};
+
+/// The address of an S3 object, for building its URL and signing requests
+/// to it. Virtual-hosted style, with the bucket in the host name, is used
+/// when possible; path style, with the bucket in the path, when the bucket
+/// name isn't a valid host name label or contains dots (which the TLS
+/// certificate doesn't cover), and with custom endpoints.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct S3Url {
+    bucket: String,
+    key: String,
+    region: AwsRegion,
+    endpoint: Option<(String, String)>,
+    path_style: bool,
+}
+
+impl S3Url {
+    pub fn new(bucket: impl Into<String>, key: impl Into<String>, region: AwsRegion) -> S3Url {
+        S3Url {
+            bucket: bucket.into(),
+            key: key.into(),
+            region,
+            endpoint: None,
+            path_style: false,
+        }
+    }
+
+    /// Sends the requests to an S3-compatible service, like
+    /// `http://localhost:9000`, instead of AWS.
+    pub fn with_endpoint(mut self, endpoint: &str) -> S3Url {
+        let (scheme, authority) = match endpoint.find("://") {
+            Some(i) => (&endpoint[..i], &endpoint[i + 3..]),
+            None => ("https", endpoint),
+        };
+        let authority = authority.trim_end_matches('/');
+        self.endpoint = Some((scheme.to_owned(), authority.to_owned()));
+        self
+    }
+
+    /// Uses path style even when virtual-hosted style would work.
+    pub fn with_path_style(mut self) -> S3Url {
+        self.path_style = true;
+        self
+    }
+
+    pub fn is_path_style(&self) -> bool {
+        // Dots aren't allowed, as the TLS certificate only covers one level
+        self.path_style || self.endpoint.is_some() || !is_host_label(&self.bucket)
+    }
+
+    /// The value of the `host` header.
+    pub fn host(&self) -> String {
+        let service_host = match &self.endpoint {
+            Some((_, authority)) => authority.clone(),
+            None => format!("s3.{}.amazonaws.com", self.region.to_str()),
+        };
+        if self.is_path_style() {
+            service_host
+        } else {
+            format!("{}.{}", self.bucket, service_host)
+        }
+    }
+
+    /// The path to sign, not yet URL-encoded; the signer encodes it for
+    /// the canonical request.
+    pub fn abspath(&self) -> Vec<u8> {
+        let mut abspath = Vec::with_capacity(self.bucket.len() + self.key.len() + 2);
+        if self.is_path_style() {
+            abspath.push(b'/');
+            abspath.extend_from_slice(self.bucket.as_bytes());
+        }
+        abspath.push(b'/');
+        abspath.extend_from_slice(self.key.as_bytes());
+        abspath
+    }
+
+    /// The URL of the object, with the key encoded the same way as in the
+    /// signature.
+    pub fn url(&self) -> String {
+        let scheme = match &self.endpoint {
+            Some((scheme, _)) => scheme,
+            None => "https",
+        };
+        let mut url = format!("{}://{}", scheme, self.host()).into_bytes();
+        url.push_url_encoded_preserve_paths(&self.abspath());
+        String::from_utf8(url).expect("always ASCII after encoding")
+    }
+
+    /// Signs a request to the object at the current time of the signer's
+    /// clock, with `body` as the payload.
+    pub fn sign(
+        &self,
+        signer: &Signer,
+        method: HttpMethod,
+        query: &[(&str, &str)],
+        body: &[u8],
+    ) -> SignedRequest {
+        let host = self.host();
+        let hash = Hash::new(body);
+        let headers = [
+            (&b"host"[..], host.as_bytes()),
+            (&b"x-amz-content-sha256"[..], hash.as_hex()),
+        ];
+        let mut buffer = Vec::new();
+        let signed = signer.sign(
+            &mut buffer,
+            method,
+            &self.abspath(),
+            &sorted_query(query),
+            &headers,
+            &hash,
+        );
+
+        let content_sha256 = String::from_utf8(hash.as_hex().to_vec()).expect("always ASCII");
+        SignedRequest::new(
+            method,
+            self.url_with_query(query),
+            signed,
+            vec![("x-amz-content-sha256", content_sha256)],
+        )
+    }
+
+    /// Presigns a request to the object at the current time of the
+    /// signer's clock, leaving the payload unsigned. Returns the URL.
+    pub fn presign(
+        &self,
+        signer: &Signer,
+        method: HttpMethod,
+        query: &[(&str, &str)],
+        expires_in: Duration,
+    ) -> String {
+        let mut buffer = Vec::new();
+        let presigned = signer.presign_unsigned_payload(
+            &mut buffer,
+            method,
+            self.host().as_bytes(),
+            &self.abspath(),
+            &sorted_query(query),
+            expires_in,
+        );
+        let mut url = self.url();
+        url.push('?');
+        url.push_str(std::str::from_utf8(presigned).expect("always ASCII after encoding"));
+        url
+    }
+
+    fn url_with_query(&self, query: &[(&str, &str)]) -> String {
+        let mut url = self.url().into_bytes();
+        for (i, (key, val)) in query.iter().enumerate() {
+            url.push(if i == 0 { b'?' } else { b'&' });
+            url.push_url_encoded(key.as_bytes());
+            url.push(b'=');
+            url.push_url_encoded(val.as_bytes());
+        }
+        String::from_utf8(url).expect("always ASCII after encoding")
+    }
+}
+
+fn sorted_query<'a>(query: &[(&'a str, &'a str)]) -> Vec<(&'a [u8], &'a [u8])> {
+    let mut query: Vec<_> = query
+        .iter()
+        .map(|(key, val)| (key.as_bytes(), val.as_bytes()))
+        .collect();
+    ensure_query_order(&mut query);
+    query
+}
+
+/// Whether the bucket name works as a label of a host name: lowercase
+/// letters, digits and hyphens, not at either end.
+fn is_host_label(bucket: &str) -> bool {
+    let bytes = bucket.as_bytes();
+    (3..=63).contains(&bytes.len())
+        && bytes
+            .iter()
+            .all(|&b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        && bytes[0] != b'-'
+        && bytes[bytes.len() - 1] != b'-'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_hosted_style() {
+        let url = S3Url::new(
+            "examplebucket",
+            "photos/2019/café au lait.jpg",
+            AwsRegion::EuWest1,
+        );
+        assert!(!url.is_path_style());
+        assert_eq!(url.host(), "examplebucket.s3.eu-west-1.amazonaws.com");
+        assert_eq!(url.abspath(), "/photos/2019/café au lait.jpg".as_bytes());
+        assert_eq!(
+            url.url(),
+            "https://examplebucket.s3.eu-west-1.amazonaws.com/photos/2019/caf%C3%A9%20au%20lait.jpg"
+        );
+    }
+
+    #[test]
+    fn test_path_style() {
+        let url = S3Url::new("logs.example.com", "a+b.txt", AwsRegion::UsEast1);
+        assert!(url.is_path_style());
+        assert_eq!(url.host(), "s3.us-east-1.amazonaws.com");
+        assert_eq!(
+            url.url(),
+            "https://s3.us-east-1.amazonaws.com/logs.example.com/a%2Bb.txt"
+        );
+
+        assert!(S3Url::new("Uppercase", "k", AwsRegion::UsEast1).is_path_style());
+        assert!(S3Url::new("bucket", "k", AwsRegion::UsEast1)
+            .with_path_style()
+            .is_path_style());
+
+        let url = S3Url::new("examplebucket", "dir/key", AwsRegion::UsEast1)
+            .with_endpoint("http://localhost:9000/");
+        assert_eq!(url.host(), "localhost:9000");
+        assert_eq!(url.abspath(), b"/examplebucket/dir/key");
+        assert_eq!(url.url(), "http://localhost:9000/examplebucket/dir/key");
+    }
+}
@@ -0,0 +1,340 @@
+use std::fmt;
+use std::time::Duration;
+
+use super::{S3Url, SignedRequest};
+use crate::signer::Signer;
+use crate::util::xml::{find_element, unescape, write_escaped};
+use crate::vocab::HttpMethod;
+
+/// The highest part number S3 accepts.
+const MAX_PART_NUMBER: u16 = 10000;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PartNumberError {
+    /// The part number is outside of 1 to 10000.
+    OutOfRange(u16),
+    /// The part number is listed more than once.
+    Duplicate(u16),
+}
+
+impl fmt::Display for PartNumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartNumberError::OutOfRange(part_number) => write!(
+                f,
+                "part number {} is not between 1 and {}",
+                part_number, MAX_PART_NUMBER
+            ),
+            PartNumberError::Duplicate(part_number) => {
+                write!(f, "part number {} is listed more than once", part_number)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PartNumberError {}
+
+/// An upload of an object in parts, of 5 MiB to 5 GiB each except for the
+/// last one. Start it with the request from `create_request`, upload the
+/// parts, numbered from 1 to 10000, and finish with `complete` or `abort`.
+#[derive(Clone, Debug)]
+pub struct MultipartUpload {
+    object: S3Url,
+    upload_id: String,
+}
+
+impl MultipartUpload {
+    pub fn new(object: S3Url, upload_id: impl Into<String>) -> MultipartUpload {
+        MultipartUpload {
+            object,
+            upload_id: upload_id.into(),
+        }
+    }
+
+    /// The CreateMultipartUpload request, which has an empty body.
+    pub fn create_request(object: &S3Url, signer: &Signer) -> SignedRequest {
+        object.sign(signer, HttpMethod::Post, &[("uploads", "")], b"")
+    }
+
+    /// Reads the upload ID from the response to the CreateMultipartUpload
+    /// request.
+    pub fn from_create_response(object: S3Url, body: &[u8]) -> Option<MultipartUpload> {
+        let upload_id = unescape(find_element(body, b"UploadId")?)?;
+        let upload_id = String::from_utf8(upload_id).ok()?;
+        Some(MultipartUpload::new(object, upload_id))
+    }
+
+    pub fn upload_id(&self) -> &str {
+        &self.upload_id
+    }
+
+    /// The UploadPart request for the contents of `part`. Keep the `ETag`
+    /// header of the response for completing the upload.
+    pub fn upload_part(
+        &self,
+        signer: &Signer,
+        part_number: u16,
+        part: &[u8],
+    ) -> Result<SignedRequest, PartNumberError> {
+        let part_number = check_part_number(part_number)?.to_string();
+        Ok(self.object.sign(
+            signer,
+            HttpMethod::Put,
+            &self.part_query(&part_number),
+            part,
+        ))
+    }
+
+    /// A presigned URL for uploading a part with `PUT`, so that a browser
+    /// can upload it directly. It can then read the `ETag` header of the
+    /// response if the bucket's CORS configuration exposes it.
+    pub fn presign_part(
+        &self,
+        signer: &Signer,
+        part_number: u16,
+        expires_in: Duration,
+    ) -> Result<String, PartNumberError> {
+        let part_number = check_part_number(part_number)?.to_string();
+        Ok(self.object.presign(
+            signer,
+            HttpMethod::Put,
+            &self.part_query(&part_number),
+            expires_in,
+        ))
+    }
+
+    /// The CompleteMultipartUpload request, to send with the body from
+    /// `complete_body`.
+    pub fn complete(&self, signer: &Signer, body: &[u8]) -> SignedRequest {
+        self.object.sign(
+            signer,
+            HttpMethod::Post,
+            &[("uploadId", &self.upload_id)],
+            body,
+        )
+    }
+
+    /// The body of the CompleteMultipartUpload request, listing the part
+    /// numbers and `ETag`s of the uploaded parts. S3 requires them in
+    /// ascending order, so they're sorted. Each part number has to be in
+    /// range and listed once.
+    pub fn complete_body(parts: &[(u16, &str)]) -> Result<Vec<u8>, PartNumberError> {
+        let mut parts = parts.to_vec();
+        parts.sort_by_key(|(part_number, _)| *part_number);
+        for (i, (part_number, _)) in parts.iter().enumerate() {
+            check_part_number(*part_number)?;
+            if i > 0 && parts[i - 1].0 == *part_number {
+                return Err(PartNumberError::Duplicate(*part_number));
+            }
+        }
+        let mut body = Vec::new();
+        body.extend_from_slice(
+            b"<CompleteMultipartUpload xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">",
+        );
+        for (part_number, etag) in parts {
+            body.extend_from_slice(b"<Part><PartNumber>");
+            body.extend_from_slice(part_number.to_string().as_bytes());
+            body.extend_from_slice(b"</PartNumber><ETag>");
+            write_escaped(&mut body, etag.as_bytes());
+            body.extend_from_slice(b"</ETag></Part>");
+        }
+        body.extend_from_slice(b"</CompleteMultipartUpload>");
+        Ok(body)
+    }
+
+    /// The AbortMultipartUpload request, which has an empty body. It frees
+    /// the storage of the parts uploaded so far.
+    pub fn abort(&self, signer: &Signer) -> SignedRequest {
+        self.object.sign(
+            signer,
+            HttpMethod::Delete,
+            &[("uploadId", &self.upload_id)],
+            b"",
+        )
+    }
+
+    fn part_query<'a>(&'a self, part_number: &'a str) -> [(&'a str, &'a str); 2] {
+        [("partNumber", part_number), ("uploadId", &self.upload_id)]
+    }
+}
+
+fn check_part_number(part_number: u16) -> Result<u16, PartNumberError> {
+    if (1..=MAX_PART_NUMBER).contains(&part_number) {
+        Ok(part_number)
+    } else {
+        Err(PartNumberError::OutOfRange(part_number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::tests::test_signer;
+    use crate::vocab::{AwsRegion, AwsService, Hash};
+
+    const UPLOAD_ID: &str = "VXBsb2FkIElEIGZvciA2aWWpbmcncyBteS1tb3ZpZS5tMnRzIHVwbG9hZA";
+
+    fn signer() -> Signer {
+        test_signer(AwsRegion::UsWest2, AwsService::S3)
+    }
+
+    fn object() -> S3Url {
+        S3Url::new("examplebucket", "videos/my movie.m2ts", AwsRegion::UsWest2)
+    }
+
+    #[test]
+    fn test_create() {
+        let request = MultipartUpload::create_request(&object(), &signer());
+        assert_eq!(request.method, HttpMethod::Post);
+        assert_eq!(
+            request.url,
+            "https://examplebucket.s3.us-west-2.amazonaws.com/videos/my%20movie.m2ts?uploads="
+        );
+
+        let response = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<InitiateMultipartUploadResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">
+  <Bucket>examplebucket</Bucket>
+  <Key>videos/my movie.m2ts</Key>
+  <UploadId>{}</UploadId>
+</InitiateMultipartUploadResult>",
+            UPLOAD_ID
+        );
+        let upload = MultipartUpload::from_create_response(object(), response.as_bytes()).unwrap();
+        assert_eq!(upload.upload_id(), UPLOAD_ID);
+        assert!(MultipartUpload::from_create_response(object(), b"<Error/>").is_none());
+
+        let upload = MultipartUpload::from_create_response(
+            object(),
+            b"<UploadId>a&amp;b&lt;c&gt;&#x2F;&#61;</UploadId>",
+        )
+        .unwrap();
+        assert_eq!(upload.upload_id(), "a&b<c>/=");
+    }
+
+    #[test]
+    fn test_upload_part() {
+        let upload = MultipartUpload::new(object(), UPLOAD_ID);
+        let signer = signer();
+        let request = upload.upload_part(&signer, 2, b"part two").unwrap();
+        assert_eq!(request.method, HttpMethod::Put);
+        assert_eq!(
+            request.url,
+            format!(
+                "https://examplebucket.s3.us-west-2.amazonaws.com/videos/my%20movie.m2ts?partNumber=2&uploadId={}",
+                UPLOAD_ID
+            )
+        );
+
+        let hash = Hash::new(b"part two");
+        let mut buffer = Vec::new();
+        let expected = signer.sign(
+            &mut buffer,
+            HttpMethod::Put,
+            b"/videos/my movie.m2ts",
+            &[(b"partNumber", b"2"), (b"uploadId", UPLOAD_ID.as_bytes())],
+            &[
+                (b"host", b"examplebucket.s3.us-west-2.amazonaws.com"),
+                (b"x-amz-content-sha256", hash.as_hex()),
+            ],
+            &hash,
+        );
+        let header = |name| {
+            request
+                .headers
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.as_bytes())
+        };
+        assert_eq!(header("authorization"), Some(expected.authorization));
+        assert_eq!(header("x-amz-date"), Some(&b"20150830T123600Z"[..]));
+        assert_eq!(header("x-amz-content-sha256"), Some(&hash.as_hex()[..]));
+        assert_eq!(header("x-amz-security-token"), None);
+    }
+
+    #[test]
+    fn test_presign_part() {
+        let upload = MultipartUpload::new(object(), UPLOAD_ID);
+        let signer = signer();
+        let url = upload
+            .presign_part(&signer, 3, Duration::from_secs(900))
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        let expected = signer.presign_unsigned_payload(
+            &mut buffer,
+            HttpMethod::Put,
+            b"examplebucket.s3.us-west-2.amazonaws.com",
+            b"/videos/my movie.m2ts",
+            &[(b"partNumber", b"3"), (b"uploadId", UPLOAD_ID.as_bytes())],
+            Duration::from_secs(900),
+        );
+        assert_eq!(
+            url,
+            format!(
+                "https://examplebucket.s3.us-west-2.amazonaws.com/videos/my%20movie.m2ts?{}",
+                std::str::from_utf8(expected).unwrap()
+            )
+        );
+        assert!(url.contains("?X-Amz-Algorithm=AWS4-HMAC-SHA256&"));
+        assert!(url.contains("&X-Amz-Expires=900&X-Amz-SignedHeaders=host&partNumber=3&uploadId="));
+    }
+
+    #[test]
+    fn test_part_number_range() {
+        let upload = MultipartUpload::new(object(), UPLOAD_ID);
+        let signer = signer();
+        assert_eq!(
+            upload.upload_part(&signer, 0, b"").unwrap_err(),
+            PartNumberError::OutOfRange(0)
+        );
+        assert_eq!(
+            upload
+                .presign_part(&signer, 10001, Duration::from_secs(900))
+                .unwrap_err(),
+            PartNumberError::OutOfRange(10001)
+        );
+        assert!(upload.upload_part(&signer, 10000, b"").is_ok());
+
+        assert_eq!(
+            MultipartUpload::complete_body(&[(1, "\"a\""), (0, "\"b\"")]).unwrap_err(),
+            PartNumberError::OutOfRange(0)
+        );
+        assert_eq!(
+            MultipartUpload::complete_body(&[(2, "\"a\""), (1, "\"b\""), (2, "\"c\"")])
+                .unwrap_err(),
+            PartNumberError::Duplicate(2)
+        );
+    }
+
+    #[test]
+    fn test_complete_and_abort() {
+        let body = MultipartUpload::complete_body(&[
+            (2, "\"0c78aef83f66abc1fa1e8477f296d394\""),
+            (1, "\"a54357aff0632cce46d942af68356b38\""),
+        ])
+        .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            "<CompleteMultipartUpload xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\">\
+<Part><PartNumber>1</PartNumber><ETag>\"a54357aff0632cce46d942af68356b38\"</ETag></Part>\
+<Part><PartNumber>2</PartNumber><ETag>\"0c78aef83f66abc1fa1e8477f296d394\"</ETag></Part>\
+</CompleteMultipartUpload>"
+        );
+
+        let upload = MultipartUpload::new(object(), UPLOAD_ID);
+        let request = upload.complete(&signer(), &body);
+        assert_eq!(request.method, HttpMethod::Post);
+        assert!(request.url.ends_with(&format!("?uploadId={}", UPLOAD_ID)));
+        let content_sha256 = request
+            .headers
+            .iter()
+            .find(|(key, _)| *key == "x-amz-content-sha256")
+            .map(|(_, value)| value.as_bytes());
+        assert_eq!(content_sha256, Some(&Hash::new(&body).as_hex()[..]));
+
+        let request = upload.abort(&signer());
+        assert_eq!(request.method, HttpMethod::Delete);
+        assert!(request.url.ends_with(&format!("?uploadId={}", UPLOAD_ID)));
+    }
+}
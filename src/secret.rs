@@ -0,0 +1,79 @@
+use std::fmt;
+
+use crate::util::wipe;
+
+/// A secret access key. The memory is zeroed when dropped, and the key never
+/// shows up in `Debug` or `Display` output. Created by `validate_secret_key`.
+#[derive(Clone)]
+pub struct SecretAccessKey(pub(crate) [u8; 40]);
+
+impl SecretAccessKey {
+    pub fn as_bytes(&self) -> &[u8; 40] {
+        &self.0
+    }
+}
+
+impl Drop for SecretAccessKey {
+    fn drop(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
+impl fmt::Debug for SecretAccessKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl fmt::Display for SecretAccessKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+/// A session token that comes with temporary credentials. Like
+/// `SecretAccessKey`, it's zeroed when dropped and redacted when printed.
+#[derive(Clone)]
+pub struct SessionToken(Vec<u8>);
+
+impl SessionToken {
+    /// Takes ownership of the token; passing in a `String` or `Vec<u8>`
+    /// avoids leaving unwiped copies around.
+    pub fn new(token: impl Into<Vec<u8>>) -> SessionToken {
+        SessionToken(token.into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Drop for SessionToken {
+    fn drop(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
+impl fmt::Debug for SessionToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl fmt::Display for SessionToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+#[test]
+fn test_redacted() {
+    let secret = crate::validate_secret_key(b"wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY").unwrap();
+    let token = SessionToken::new("AQoDYXdzEJr...<remainder of session token>");
+    assert_eq!(format!("{:?} {}", secret, secret), "*** ***");
+    assert_eq!(format!("{:?} {}", token, token), "*** ***");
+    assert_eq!(
+        &secret.as_bytes()[..],
+        &b"wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY"[..]
+    );
+}
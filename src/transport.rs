@@ -0,0 +1,493 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::util::{ord_ignore_case, wipe};
+use crate::vocab::HttpMethod;
+
+pub struct HttpRequest<'a> {
+    pub method: HttpMethod,
+    pub url: &'a [u8],
+    pub headers: &'a [(&'a [u8], &'a [u8])],
+    pub body: &'a [u8],
+}
+
+#[derive(Debug)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(Vec<u8>, Vec<u8>)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Returns the value of the first header with the name, ignoring case.
+    pub fn header(&self, name: &[u8]) -> Option<&[u8]> {
+        self.headers
+            .iter()
+            .find(|(key, _)| ord_ignore_case(key, name).is_eq())
+            .map(|(_, val)| &val[..])
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Sends the HTTP requests of the credential providers. The default
+/// `PlainHttpClient` only speaks plain HTTP, which is enough for the
+/// link-local endpoints on EC2, ECS and EKS. Implement this on top of an
+/// existing client to use providers that talk to HTTPS endpoints.
+pub trait HttpClient: Send + Sync {
+    fn send(&self, request: &HttpRequest<'_>) -> io::Result<HttpResponse>;
+}
+
+/// A bare-bones blocking HTTP/1.1 client that opens a new connection for
+/// every request. Supports only `http://` URLs.
+#[derive(Clone, Debug)]
+pub struct PlainHttpClient {
+    timeout: Duration,
+    max_response_size: usize,
+}
+
+impl Default for PlainHttpClient {
+    fn default() -> PlainHttpClient {
+        PlainHttpClient::new()
+    }
+}
+
+impl PlainHttpClient {
+    pub fn new() -> PlainHttpClient {
+        PlainHttpClient {
+            timeout: Duration::from_secs(5),
+            max_response_size: 1024 * 1024,
+        }
+    }
+
+    /// Sets the timeout used for connecting, and for each read and write.
+    pub fn with_timeout(mut self, timeout: Duration) -> PlainHttpClient {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the size limit of responses, headers included. Larger responses
+    /// are an error. Defaults to 1 MiB.
+    pub fn with_max_response_size(mut self, max_response_size: usize) -> PlainHttpClient {
+        self.max_response_size = max_response_size;
+        self
+    }
+}
+
+impl HttpClient for PlainHttpClient {
+    fn send(&self, request: &HttpRequest<'_>) -> io::Result<HttpResponse> {
+        let (authority, path_and_query) = split_http_url(request.url)?;
+        // Line breaks would end the header and start another one
+        let has_line_break = |bytes: &[u8]| bytes.iter().any(|&b| b == b'\r' || b == b'\n');
+        if has_line_break(request.url)
+            || request
+                .headers
+                .iter()
+                .any(|(key, val)| has_line_break(key) || has_line_break(val))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "line break in URL or header",
+            ));
+        }
+
+        let mut message = Vec::new();
+        message.extend_from_slice(request.method.to_bytes());
+        message.push(b' ');
+        message.extend_from_slice(path_and_query);
+        message.extend_from_slice(b" HTTP/1.1\r\nHost: ");
+        message.extend_from_slice(authority);
+        message.extend_from_slice(b"\r\nConnection: close\r\n");
+        if !request.body.is_empty() || request.method != HttpMethod::Get {
+            message.extend_from_slice(b"Content-Length: ");
+            message.extend_from_slice(request.body.len().to_string().as_bytes());
+            message.extend_from_slice(b"\r\n");
+        }
+        for (key, val) in request.headers {
+            message.extend_from_slice(key);
+            message.extend_from_slice(b": ");
+            message.extend_from_slice(val);
+            message.extend_from_slice(b"\r\n");
+        }
+        message.extend_from_slice(b"\r\n");
+        message.extend_from_slice(request.body);
+
+        let mut stream = self.connect(authority)?;
+        let written = stream.write_all(&message);
+        // The headers might carry tokens
+        wipe(&mut message);
+        written?;
+
+        let mut raw = Vec::new();
+        let read = stream
+            .take(self.max_response_size as u64 + 1)
+            .read_to_end(&mut raw);
+        if read.is_ok() && raw.len() > self.max_response_size {
+            wipe(&mut raw);
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "HTTP response too large",
+            ));
+        }
+        let response = read.and_then(|_| parse_response(&raw));
+        wipe(&mut raw);
+        response
+    }
+}
+
+impl PlainHttpClient {
+    fn connect(&self, authority: &[u8]) -> io::Result<TcpStream> {
+        let invalid_host = || io::Error::new(io::ErrorKind::InvalidInput, "invalid host");
+        let (host, port) = split_host_port(authority);
+        let host = std::str::from_utf8(host).map_err(|_| invalid_host())?;
+        let port = match port {
+            Some(port) => std::str::from_utf8(port)
+                .ok()
+                .and_then(|port| port.parse().ok())
+                .ok_or_else(invalid_host)?,
+            None => 80,
+        };
+        let addrs = (host, port).to_socket_addrs()?;
+
+        let mut last_err = io::Error::new(io::ErrorKind::NotFound, "host not found");
+        for addr in addrs {
+            match TcpStream::connect_timeout(&addr, self.timeout) {
+                Ok(stream) => {
+                    stream.set_read_timeout(Some(self.timeout))?;
+                    stream.set_write_timeout(Some(self.timeout))?;
+                    return Ok(stream);
+                }
+                Err(err) => last_err = err,
+            }
+        }
+        Err(last_err)
+    }
+}
+
+/// Splits the authority part of a URL into the host and the port, if there is
+/// one. The brackets around IPv6 addresses are removed.
+pub(crate) fn split_host_port(authority: &[u8]) -> (&[u8], Option<&[u8]>) {
+    // IPv6 addresses have colons of their own, but they are enclosed in brackets
+    let (host, port) = match authority.iter().rposition(|&b| b == b':') {
+        Some(colon) if !authority[colon..].contains(&b']') => {
+            (&authority[..colon], Some(&authority[colon + 1..]))
+        }
+        _ => (authority, None),
+    };
+    let host = host.strip_prefix(b"[").unwrap_or(host);
+    let host = host.strip_suffix(b"]").unwrap_or(host);
+    (host, port)
+}
+
+/// Splits a `http://` URL into the authority (host and port) and the rest.
+pub(crate) fn split_http_url(url: &[u8]) -> io::Result<(&[u8], &[u8])> {
+    if !url.starts_with(b"http://") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "only http:// URLs are supported",
+        ));
+    }
+    let url = &url[b"http://".len()..];
+    let split_idx = url
+        .iter()
+        .position(|&b| b == b'/' || b == b'?')
+        .unwrap_or(url.len());
+    match &url[split_idx..] {
+        b"" => Ok((&url[..split_idx], b"/")),
+        path => Ok((&url[..split_idx], path)),
+    }
+}
+
+fn invalid_response() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid HTTP response")
+}
+
+fn parse_response(raw: &[u8]) -> io::Result<HttpResponse> {
+    let head_len = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(invalid_response)?;
+    let mut lines = raw[..head_len].split(|&b| b == b'\n');
+
+    // Status line: HTTP/1.1 200 OK
+    let status_line = lines.next().ok_or_else(invalid_response)?;
+    let status = status_line
+        .split(|&b| b == b' ')
+        .nth(1)
+        .and_then(|status| std::str::from_utf8(status).ok())
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(invalid_response)?;
+
+    let mut headers = Vec::new();
+    for line in lines {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let colon = line
+            .iter()
+            .position(|&b| b == b':')
+            .ok_or_else(invalid_response)?;
+        let val = &line[colon + 1..];
+        let val_start = val
+            .iter()
+            .position(|&b| b != b' ' && b != b'\t')
+            .unwrap_or(val.len());
+        headers.push((line[..colon].to_vec(), val[val_start..].to_vec()));
+    }
+
+    let mut response = HttpResponse {
+        status,
+        headers,
+        body: Vec::new(),
+    };
+    let body = &raw[head_len + 4..];
+    if response
+        .header(b"transfer-encoding")
+        .is_some_and(|te| ord_ignore_case(te, b"chunked").is_eq())
+    {
+        response.body = decode_chunked(body)?;
+    } else if let Some(len) = response.header(b"content-length") {
+        let len: usize = std::str::from_utf8(len)
+            .ok()
+            .and_then(|len| len.trim().parse().ok())
+            .ok_or_else(invalid_response)?;
+        response.body = body.get(..len).ok_or_else(invalid_response)?.to_vec();
+    } else {
+        response.body = body.to_vec();
+    }
+    Ok(response)
+}
+
+fn decode_chunked(mut input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    loop {
+        let line_len = input
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or_else(invalid_response)?;
+        // Chunk extensions after ';' are ignored
+        let size = input[..line_len]
+            .split(|&b| b == b';')
+            .next()
+            .and_then(|size| std::str::from_utf8(size).ok())
+            .and_then(|size| usize::from_str_radix(size.trim(), 16).ok())
+            .ok_or_else(invalid_response)?;
+        input = &input[line_len + 2..];
+        if size == 0 {
+            return Ok(output);
+        }
+        output.extend_from_slice(input.get(..size).ok_or_else(invalid_response)?);
+        input = input.get(size + 2..).ok_or_else(invalid_response)?;
+    }
+}
+
+/// A local stand-in for the HTTP endpoints that the providers talk to.
+#[cfg(test)]
+pub(crate) mod test_server {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Serves requests on a local port in a background thread, responding
+    /// with whatever the handler returns for the raw request. Returns the
+    /// `http://` base URL of the server.
+    pub fn serve(mut handler: impl FnMut(&[u8]) -> Vec<u8> + Send + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let request = read_request(&mut stream);
+                let response = handler(&request);
+                stream.write_all(&response).unwrap();
+            }
+        });
+        url
+    }
+
+    fn read_request(stream: &mut impl Read) -> Vec<u8> {
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        loop {
+            let n = stream.read(&mut buf).unwrap();
+            request.extend_from_slice(&buf[..n]);
+            if let Some(head_len) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                let head = String::from_utf8_lossy(&request[..head_len]).to_lowercase();
+                let content_length = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length: "))
+                    .map_or(0, |len| len.trim().parse().unwrap());
+                if request.len() >= head_len + 4 + content_length || n == 0 {
+                    return request;
+                }
+            } else if n == 0 {
+                return request;
+            }
+        }
+    }
+
+    pub fn response(status: u16, body: &str) -> Vec<u8> {
+        format!(
+            "HTTP/1.1 {} Whatever\r\nContent-Length: {}\r\nContent-Type: application/json\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+        .into_bytes()
+    }
+
+    /// Returns the request line, like `GET /path HTTP/1.1`.
+    pub fn request_line(request: &[u8]) -> String {
+        String::from_utf8_lossy(request)
+            .lines()
+            .next()
+            .unwrap_or("")
+            .to_owned()
+    }
+
+    /// Returns the value of a request header, ignoring case of the name.
+    pub fn request_header(request: &[u8], name: &str) -> Option<String> {
+        let request = String::from_utf8_lossy(request);
+        let head = request.split("\r\n\r\n").next().unwrap_or("");
+        head.lines().skip(1).find_map(|line| {
+            let mut split = line.splitn(2, ':');
+            let key = split.next()?;
+            if key.eq_ignore_ascii_case(name) {
+                Some(split.next()?.trim().to_owned())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the body of the request.
+    pub fn request_body(request: &[u8]) -> &[u8] {
+        let head_len = request.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        &request[head_len + 4..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_http_url() {
+        assert_eq!(
+            split_http_url(b"http://169.254.170.2/v2/credentials/id").unwrap(),
+            (&b"169.254.170.2"[..], &b"/v2/credentials/id"[..])
+        );
+        assert_eq!(
+            split_http_url(b"http://[fd00:ec2::254]").unwrap(),
+            (&b"[fd00:ec2::254]"[..], &b"/"[..])
+        );
+        assert_eq!(
+            split_http_url(b"http://localhost:8080?a=b").unwrap(),
+            (&b"localhost:8080"[..], &b"?a=b"[..])
+        );
+        assert!(split_http_url(b"https://sts.amazonaws.com/").is_err());
+    }
+
+    #[test]
+    fn test_split_host_port() {
+        assert_eq!(split_host_port(b"localhost"), (&b"localhost"[..], None));
+        assert_eq!(
+            split_host_port(b"127.0.0.1:8080"),
+            (&b"127.0.0.1"[..], Some(&b"8080"[..]))
+        );
+        assert_eq!(
+            split_host_port(b"[fd00:ec2::254]"),
+            (&b"fd00:ec2::254"[..], None)
+        );
+        assert_eq!(
+            split_host_port(b"[::1]:80"),
+            (&b"::1"[..], Some(&b"80"[..]))
+        );
+    }
+
+    #[test]
+    fn test_parse_chunked_response() {
+        let response = parse_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nX-Test:  value \r\n\r\n5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.header(b"x-test"), Some(&b"value "[..]));
+        assert_eq!(&response.body[..], &b"hello, world"[..]);
+
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort").is_err());
+        assert!(parse_response(b"garbage").is_err());
+    }
+
+    #[test]
+    fn test_plain_http_client() {
+        let url = test_server::serve(|request| {
+            assert_eq!(
+                test_server::request_line(request),
+                "PUT /latest/api/token?x=1 HTTP/1.1"
+            );
+            assert_eq!(
+                test_server::request_header(request, "x-custom").as_deref(),
+                Some("yes")
+            );
+            assert_eq!(test_server::request_body(request), b"body");
+            test_server::response(201, "created")
+        });
+        let url = format!("{}/latest/api/token?x=1", url);
+        let response = PlainHttpClient::new()
+            .send(&HttpRequest {
+                method: HttpMethod::Put,
+                url: url.as_bytes(),
+                headers: &[(b"X-Custom", b"yes")],
+                body: b"body",
+            })
+            .unwrap();
+        assert_eq!(response.status, 201);
+        assert!(response.is_success());
+        assert_eq!(&response.body[..], &b"created"[..]);
+    }
+
+    #[test]
+    fn test_rejects_line_breaks() {
+        let client = PlainHttpClient::new();
+        for (url, headers) in [
+            (
+                &b"http://127.0.0.1:9/"[..],
+                &[(&b"Authorization"[..], &b"token\r\nX-Evil: 1"[..])][..],
+            ),
+            (b"http://127.0.0.1:9/", &[(b"X-Evil\n", b"1")]),
+            (b"http://127.0.0.1:9/\r\nX-Evil: 1", &[]),
+        ] {
+            let err = client
+                .send(&HttpRequest {
+                    method: HttpMethod::Get,
+                    url,
+                    headers,
+                    body: b"",
+                })
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_response_size_limit() {
+        let url = test_server::serve(|_| test_server::response(200, &"x".repeat(1000)));
+        let request = HttpRequest {
+            method: HttpMethod::Get,
+            url: url.as_bytes(),
+            headers: &[],
+            body: b"",
+        };
+        let err = PlainHttpClient::new()
+            .with_max_response_size(500)
+            .send(&request)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let response = PlainHttpClient::new()
+            .with_max_response_size(2000)
+            .send(&request)
+            .unwrap();
+        assert_eq!(response.body.len(), 1000);
+    }
+}
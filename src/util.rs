@@ -1,12 +1,16 @@
 use std::cmp::Ordering;
 use std::convert::TryInto;
 use std::ops::Not;
+use std::sync::atomic::{compiler_fence, Ordering as AtomicOrdering};
 
+pub mod base64;
 pub mod hex;
 pub mod iso8601;
+pub mod json;
 pub mod url_encode;
+pub mod xml;
 
-pub use iso8601::FormatTime;
+pub use iso8601::{FormatTime, ParseTime, ParseTimeError};
 
 /// Checks if a slice is sorted according to a comparison function.
 /// Intended only for debugging.
@@ -16,7 +20,7 @@ pub fn is_sorted_by<T>(input: &[T], cmp: impl Fn(&T, &T) -> Ordering) -> bool {
         .all(|w| cmp(&w[0], &w[1]) != Ordering::Greater)
 }
 
-pub use hex::write_sha256_hex;
+pub use hex::{decode_lowercase_hex, write_lowercase_hex, write_sha256_hex, ParseHexError};
 
 pub use url_encode::ordering as ord_url_encoded;
 
@@ -37,53 +41,26 @@ pub fn whitespace(c: u8) -> bool {
     c == b'\t' || c == b' ' || c == b'\n' || c == b'\r'
 }
 
+/// Overwrites the buffer with zeroes in a way the optimizer can't elide,
+/// even if the buffer is never read again.
+pub fn wipe(buf: &mut [u8]) {
+    for byte in buf.iter_mut() {
+        // Safety: the pointer comes from a mutable reference, so it's valid and aligned
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+    compiler_fence(AtomicOrdering::SeqCst);
+}
+
 pub trait SliceExt {
-    fn trim(&self) -> &Self;
     fn no_consequent_spaces(&self) -> bool;
-    fn into_mut_array_40(&mut self) -> &mut [u8; 40];
-    fn into_array_40(&self) -> &[u8; 40];
-    fn into_mut_array_64(&mut self) -> &mut [u8; 64];
 }
 
 impl SliceExt for [u8] {
-    fn trim(&self) -> &[u8] {
-        if let Some(first) = self.iter().position(|b| !whitespace(*b)) {
-            if let Some(last) = self.iter().rposition(|b| !whitespace(*b)) {
-                &self[first..last + 1]
-            } else {
-                unreachable!();
-            }
-        } else {
-            &[]
-        }
-    }
-
     fn no_consequent_spaces(&self) -> bool {
         self.windows(2)
             .any(|w| whitespace(w[0]) && whitespace(w[1]))
             .not()
     }
-
-    // TODO: remove this once try_into supports arrays of length 40
-    fn into_mut_array_40(&mut self) -> &mut [u8; 40] {
-        assert!(self.len() == 40);
-        let reinterpret_ptr = self as *mut [u8] as *mut [u8; 40];
-        unsafe { &mut *reinterpret_ptr }
-    }
-
-    // TODO: remove this once try_into supports arrays of length 40
-    fn into_array_40(&self) -> &[u8; 40] {
-        assert!(self.len() == 40);
-        let reinterpret_ptr = self as *const [u8] as *const [u8; 40];
-        unsafe { &*reinterpret_ptr }
-    }
-
-    // TODO: remove this once try_into supports arrays of length 64
-    fn into_mut_array_64(&mut self) -> &mut [u8; 64] {
-        assert!(self.len() == 64);
-        let reinterpret_ptr = self as *mut [u8] as *mut [u8; 64];
-        unsafe { &mut *reinterpret_ptr }
-    }
 }
 
 pub trait VecExt {
@@ -111,7 +88,7 @@ impl VecExt for Vec<u8> {
     fn space_for_sha256(&mut self) -> &mut [u8; 64] {
         let offset = self.len();
         self.extend_from_slice(super::vocab::EMPTY_STR_SHA256);
-        self[offset..].into_mut_array_64()
+        (&mut self[offset..]).try_into().expect("always succeeds")
     }
 
     fn push_url_encoded(&mut self, input: &[u8]) -> &mut [u8] {
@@ -145,3 +122,10 @@ fn test_push_lowercase() {
         &b"abCD123abyz1234567890abcxyz\0\x01\x02\xff.-!@%^&*("[..]
     );
 }
+
+#[test]
+fn test_wipe() {
+    let mut secret = *b"wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+    wipe(&mut secret[..]);
+    assert_eq!(secret, [0; 40]);
+}
@@ -0,0 +1,97 @@
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const URL_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encodes the input as standard base64, with padding.
+pub fn encode(buffer: &mut Vec<u8>, input: &[u8]) {
+    encode_with(buffer, input, ALPHABET, true);
+}
+
+/// Encodes the input as URL-safe base64, without padding.
+pub fn encode_url(buffer: &mut Vec<u8>, input: &[u8]) {
+    encode_with(buffer, input, URL_ALPHABET, false);
+}
+
+fn encode_with(buffer: &mut Vec<u8>, input: &[u8], alphabet: &[u8; 64], padding: bool) {
+    buffer.reserve(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let group = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                buffer.push(alphabet[(group >> (18 - 6 * i) & 0x3f) as usize]);
+            } else if padding {
+                buffer.push(b'=');
+            }
+        }
+    }
+}
+
+/// Decodes standard base64 with padding. Fails on any other characters,
+/// including whitespace.
+#[allow(clippy::result_unit_err)]
+pub fn decode(buffer: &mut Vec<u8>, input: &[u8]) -> Result<(), ()> {
+    if !input.len().is_multiple_of(4) {
+        return Err(());
+    }
+    let value = |c: u8| ALPHABET.iter().position(|&a| a == c).map(|v| v as u32);
+    let chunks = input.len() / 4;
+    for (i, chunk) in input.chunks(4).enumerate() {
+        let padding = if i + 1 == chunks {
+            chunk.iter().rev().take_while(|&&c| c == b'=').count()
+        } else {
+            0
+        };
+        if padding > 2 {
+            return Err(());
+        }
+        let mut group = 0;
+        for &c in &chunk[..4 - padding] {
+            group = group << 6 | value(c).ok_or(())?;
+        }
+        group <<= 6 * padding;
+        let bytes = [(group >> 16) as u8, (group >> 8) as u8, group as u8];
+        buffer.extend_from_slice(&bytes[..3 - padding]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        // Test vectors from RFC 4648
+        let vectors: [(&[u8], &[u8]); 7] = [
+            (b"", b""),
+            (b"f", b"Zg=="),
+            (b"fo", b"Zm8="),
+            (b"foo", b"Zm9v"),
+            (b"foob", b"Zm9vYg=="),
+            (b"fooba", b"Zm9vYmE="),
+            (b"foobar", b"Zm9vYmFy"),
+        ];
+        for (input, output) in vectors.iter() {
+            let mut buffer = Vec::new();
+            encode(&mut buffer, input);
+            assert_eq!(&buffer[..], *output);
+            let mut decoded = Vec::new();
+            decode(&mut decoded, output).unwrap();
+            assert_eq!(&decoded[..], *input);
+        }
+        for invalid in [&b"Zg="[..], b"Z===", b"Zg==Zg==", b"Zm9v\n", b"Zm-v"].iter() {
+            assert!(decode(&mut Vec::new(), invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn test_encode_url() {
+        let mut buffer = Vec::new();
+        encode_url(&mut buffer, b"\xfb\xff?f");
+        assert_eq!(&buffer[..], b"-_8_Zg");
+    }
+}
@@ -1,12 +1,5 @@
-fn chunk_sha256(input_buf: &[u8; 32]) -> &[u32; 8] {
-    let reinterpret_ptr = input_buf as *const [u8; 32] as *const [u32; 8];
-    unsafe { &*reinterpret_ptr }
-}
-
-fn chunk_mut_hex_sha256(input_buf: &mut [u8; 64]) -> &mut [u64; 8] {
-    let reinterpret_ptr = input_buf as *mut [u8; 64] as *mut [u64; 8];
-    unsafe { &mut *reinterpret_ptr }
-}
+use std::convert::TryInto;
+use std::fmt;
 
 // TODO: try multiplication method: http://0x80.pl/articles/convert-to-hex.html (Split nibbles: x86 - plain)
 // TODO: shift nibble mask instead of input, to save from shifting back
@@ -43,7 +36,7 @@ fn nibbles_to_lowercase_hex(nibbles: u64) -> u64 {
     let msb = tmp & packed(0x80);
     let mask = msb - (msb >> 7);
 
-    return ascii09 + (mask & CORRECTION);
+    ascii09 + (mask & CORRECTION)
 }
 
 pub fn nibble_to_uppercase_hex(low: u8) -> u8 {
@@ -56,7 +49,7 @@ pub fn nibble_to_uppercase_hex(low: u8) -> u8 {
     let msb = tmp & 0x80;
     let mask = msb - (msb >> 7);
 
-    return ascii09 + (mask & CORRECTION);
+    ascii09 + (mask & CORRECTION)
 }
 
 fn write_u32_lowercase_hex(input: u32) -> u64 {
@@ -65,14 +58,61 @@ fn write_u32_lowercase_hex(input: u32) -> u64 {
 }
 
 pub fn write_sha256_hex(input_buf: &[u8; 32], output_buf: &mut [u8; 64]) {
-    let io = chunk_sha256(input_buf)
-        .iter()
-        .zip(chunk_mut_hex_sha256(output_buf));
-    for (input, output) in io {
-        *output = write_u32_lowercase_hex(*input);
+    write_lowercase_hex(input_buf, output_buf);
+}
+
+/// Writes `input` as lowercase hex into `output`, which must be twice as
+/// long.
+pub fn write_lowercase_hex(input_buf: &[u8], output_buf: &mut [u8]) {
+    assert_eq!(output_buf.len(), input_buf.len() * 2);
+    let input = input_buf.chunks_exact(4);
+    let remainder = input.remainder();
+    let mut output = output_buf.chunks_exact_mut(8);
+    for (input, output) in input.zip(&mut output) {
+        let input = u32::from_ne_bytes(input.try_into().expect("always succeeds"));
+        output.copy_from_slice(&write_u32_lowercase_hex(input).to_ne_bytes());
+    }
+    // Pad the last few bytes to a whole word and keep only their digits
+    if !remainder.is_empty() {
+        let mut input = [0; 4];
+        input[..remainder.len()].copy_from_slice(remainder);
+        let hex = write_u32_lowercase_hex(u32::from_ne_bytes(input)).to_ne_bytes();
+        let output = output.into_remainder();
+        output.copy_from_slice(&hex[..output.len()]);
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ParseHexError;
+
+impl fmt::Display for ParseHexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid lowercase hex")
     }
 }
 
+impl std::error::Error for ParseHexError {}
+
+fn lowercase_hex_to_nibble(hex: u8) -> Result<u8, ParseHexError> {
+    match hex {
+        b'0'..=b'9' => Ok(hex - b'0'),
+        b'a'..=b'f' => Ok(hex - b'a' + 10),
+        _ => Err(ParseHexError),
+    }
+}
+
+/// Decodes lowercase hex into `output`, which must be exactly half the length
+/// of the input. SigV4 always uses lowercase hex, so uppercase is rejected.
+pub fn decode_lowercase_hex(input: &[u8], output: &mut [u8]) -> Result<(), ParseHexError> {
+    if input.len() != output.len() * 2 {
+        return Err(ParseHexError);
+    }
+    for (pair, byte) in input.chunks_exact(2).zip(output) {
+        *byte = lowercase_hex_to_nibble(pair[0])? << 4 | lowercase_hex_to_nibble(pair[1])?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -172,4 +212,53 @@ mod tests {
             &b"ff00f00f06c0309214f805bb90ccff089219ecd68b2577efef23edd43b7e1a59"[..]
         );
     }
+
+    #[test]
+    fn test_write_lowercase_hex() {
+        for len in 0..10 {
+            let input: Vec<u8> = (0..len)
+                .map(|i| (0x1f * i as u8).wrapping_add(0xa0))
+                .collect();
+            let mut output = vec![0; len * 2];
+            write_lowercase_hex(&input, &mut output);
+            let expected: String = input.iter().map(|byte| format!("{:02x}", byte)).collect();
+            assert_eq!(std::str::from_utf8(&output).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_lowercase_hex() {
+        let mut buffer = [0u8; 32];
+        decode_lowercase_hex(
+            b"ff00f00f06c0309214f805bb90ccff089219ecd68b2577efef23edd43b7e1a59",
+            &mut buffer,
+        )
+        .unwrap();
+        let mut hex = [0; 64];
+        write_sha256_hex(&buffer, &mut hex);
+        assert_eq!(
+            &hex[..],
+            &b"ff00f00f06c0309214f805bb90ccff089219ecd68b2577efef23edd43b7e1a59"[..]
+        );
+
+        let mut buffer = [0u8; 2];
+        assert_eq!(decode_lowercase_hex(b"0a1f", &mut buffer), Ok(()));
+        assert_eq!(buffer, [0x0a, 0x1f]);
+        assert_eq!(
+            decode_lowercase_hex(b"0A1F", &mut buffer),
+            Err(ParseHexError)
+        );
+        assert_eq!(
+            decode_lowercase_hex(b"0a1", &mut buffer),
+            Err(ParseHexError)
+        );
+        assert_eq!(
+            decode_lowercase_hex(b"0a1f2", &mut buffer),
+            Err(ParseHexError)
+        );
+        assert_eq!(
+            decode_lowercase_hex(b"0g1f", &mut buffer),
+            Err(ParseHexError)
+        );
+    }
 }
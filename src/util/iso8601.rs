@@ -1,7 +1,9 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub const YYYYMMDD: [u8; 8] = *b"00000000";
 pub const BASIC_FORMAT_SEC_UTC: [u8; 16] = *b"00000000T000000Z";
+pub const EXTENDED_FORMAT_MILLIS_UTC: [u8; 24] = *b"0000-00-00T00:00:00.000Z";
 
 pub trait FormatTime {
     fn write_yyyymmdd(&self, buf: &mut [u8; 8]);
@@ -9,6 +11,10 @@ pub trait FormatTime {
     /// ISO 8602 Basic format is a format that doesn't have separators
     /// between the fields: YYYYMMDDTHHMMSSZ
     fn write_iso8602_basic_seconds_utc(&self, buf: &mut [u8; 16]);
+
+    /// ISO 8601 Extended format with milliseconds, as used in the
+    /// expiration of POST policies: YYYY-MM-DDTHH:MM:SS.sssZ
+    fn write_iso8601_extended_millis_utc(&self, buf: &mut [u8; 24]);
 }
 
 impl FormatTime for SystemTime {
@@ -64,6 +70,227 @@ impl FormatTime for SystemTime {
         buf[14] = b'0' + (secs_of_day % 10) as u8;
         buf[15] = b'Z';
     }
+
+    fn write_iso8601_extended_millis_utc(&self, buf: &mut [u8; 24]) {
+        let mut basic = BASIC_FORMAT_SEC_UTC;
+        self.write_iso8602_basic_seconds_utc(&mut basic);
+        let millis = self
+            .duration_since(UNIX_EPOCH)
+            .expect("not supporting printing timestamps before the UNIX epoch")
+            .subsec_millis();
+
+        buf[0..4].copy_from_slice(&basic[0..4]);
+        buf[5..7].copy_from_slice(&basic[4..6]);
+        buf[8..10].copy_from_slice(&basic[6..8]);
+        buf[11..13].copy_from_slice(&basic[9..11]);
+        buf[14..16].copy_from_slice(&basic[11..13]);
+        buf[17..19].copy_from_slice(&basic[13..15]);
+        buf[20] = b'0' + (millis / 100) as u8;
+        buf[21] = b'0' + (millis / 10 % 10) as u8;
+        buf[22] = b'0' + (millis % 10) as u8;
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ParseTimeError;
+
+impl fmt::Display for ParseTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid timestamp")
+    }
+}
+
+impl std::error::Error for ParseTimeError {}
+
+pub trait ParseTime: Sized {
+    /// Parses the ISO 8601 Basic format, as used in `X-Amz-Date`:
+    /// YYYYMMDDTHHMMSSZ
+    fn parse_iso8601_basic_seconds_utc(input: &[u8]) -> Result<Self, ParseTimeError>;
+
+    /// Parses the ISO 8601 Extended format, as used in the `Expiration`
+    /// fields of credential documents: YYYY-MM-DDTHH:MM:SS, followed by
+    /// optional fractional seconds and either `Z` or a `+HH:MM`/`-HH:MM` offset
+    fn parse_iso8601_extended(input: &[u8]) -> Result<Self, ParseTimeError>;
+
+    /// Parses the RFC 1123 date format used in the HTTP `Date` header:
+    /// `Sun, 06 Nov 1994 08:49:37 GMT`
+    fn parse_http_date(input: &[u8]) -> Result<Self, ParseTimeError>;
+}
+
+impl ParseTime for SystemTime {
+    fn parse_iso8601_basic_seconds_utc(input: &[u8]) -> Result<SystemTime, ParseTimeError> {
+        if input.len() != BASIC_FORMAT_SEC_UTC.len() || input[8] != b'T' || input[15] != b'Z' {
+            return Err(ParseTimeError);
+        }
+        let year = digits(&input[0..4])?;
+        let month = digits(&input[4..6])?;
+        let mday = digits(&input[6..8])?;
+        let hour = digits(&input[9..11])?;
+        let min = digits(&input[11..13])?;
+        let sec = digits(&input[13..15])?;
+        to_system_time(year, month, mday, hour, min, sec, 0, 0)
+    }
+
+    fn parse_iso8601_extended(input: &[u8]) -> Result<SystemTime, ParseTimeError> {
+        let fixed = input.get(..19).ok_or(ParseTimeError)?;
+        if fixed[4] != b'-'
+            || fixed[7] != b'-'
+            || (fixed[10] != b'T' && fixed[10] != b't')
+            || fixed[13] != b':'
+            || fixed[16] != b':'
+        {
+            return Err(ParseTimeError);
+        }
+        let year = digits(&fixed[0..4])?;
+        let month = digits(&fixed[5..7])?;
+        let mday = digits(&fixed[8..10])?;
+        let hour = digits(&fixed[11..13])?;
+        let min = digits(&fixed[14..16])?;
+        let sec = digits(&fixed[17..19])?;
+
+        let mut rest = &input[19..];
+
+        // Fractional seconds; digits beyond nanosecond precision are ignored
+        let mut nanos = 0;
+        if rest.first() == Some(&b'.') {
+            let frac_len = rest[1..].iter().take_while(|b| b.is_ascii_digit()).count();
+            if frac_len == 0 {
+                return Err(ParseTimeError);
+            }
+            for i in 0..9 {
+                let digit = rest
+                    .get(1 + i)
+                    .filter(|_| i < frac_len)
+                    .map_or(0, |b| b - b'0');
+                nanos = nanos * 10 + u32::from(digit);
+            }
+            rest = &rest[1 + frac_len..];
+        }
+
+        // UTC offset
+        let offset_secs = match rest {
+            b"Z" | b"z" => 0,
+            [sign @ b'+', h1, h2, b':', m1, m2] | [sign @ b'-', h1, h2, b':', m1, m2] => {
+                let hours = digits(&[*h1, *h2])?;
+                let mins = digits(&[*m1, *m2])?;
+                if hours > 23 || mins > 59 {
+                    return Err(ParseTimeError);
+                }
+                let offset = hours * 3600 + mins * 60;
+                if *sign == b'+' {
+                    offset
+                } else {
+                    -offset
+                }
+            }
+            _ => return Err(ParseTimeError),
+        };
+
+        to_system_time(year, month, mday, hour, min, sec, nanos, offset_secs)
+    }
+
+    fn parse_http_date(input: &[u8]) -> Result<SystemTime, ParseTimeError> {
+        if input.len() != 29
+            || &input[3..5] != b", "
+            || input[7] != b' '
+            || input[11] != b' '
+            || input[16] != b' '
+            || input[19] != b':'
+            || input[22] != b':'
+            || &input[25..] != b" GMT"
+        {
+            return Err(ParseTimeError);
+        }
+        const WEEKDAYS: [&[u8]; 7] = [b"Mon", b"Tue", b"Wed", b"Thu", b"Fri", b"Sat", b"Sun"];
+        const MONTHS: [&[u8]; 12] = [
+            b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov",
+            b"Dec",
+        ];
+        if !WEEKDAYS.contains(&&input[0..3]) {
+            return Err(ParseTimeError);
+        }
+        let month = MONTHS
+            .iter()
+            .position(|&m| m == &input[8..11])
+            .ok_or(ParseTimeError)? as i64
+            + 1;
+        let mday = digits(&input[5..7])?;
+        let year = digits(&input[12..16])?;
+        let hour = digits(&input[17..19])?;
+        let min = digits(&input[20..22])?;
+        let sec = digits(&input[23..25])?;
+        to_system_time(year, month, mday, hour, min, sec, 0, 0)
+    }
+}
+
+fn digits(input: &[u8]) -> Result<i64, ParseTimeError> {
+    input.iter().try_fold(0, |acc, &b| {
+        if b.is_ascii_digit() {
+            Ok(acc * 10 + i64::from(b - b'0'))
+        } else {
+            Err(ParseTimeError)
+        }
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn to_system_time(
+    year: i64,
+    month: i64,
+    mday: i64,
+    hour: i64,
+    min: i64,
+    sec: i64,
+    nanos: u32,
+    offset_secs: i64,
+) -> Result<SystemTime, ParseTimeError> {
+    // Allowing for a leap second; it's folded into the next minute
+    if !(1..=12).contains(&month)
+        || mday < 1
+        || mday > days_in_month(year, month)
+        || hour > 23
+        || min > 59
+        || sec > 60
+    {
+        return Err(ParseTimeError);
+    }
+    let days = days_since_epoch(year, month, mday);
+    let secs = days * 86400 + hour * 3600 + min * 60 + sec - offset_secs;
+    if secs < 0 {
+        return Err(ParseTimeError);
+    }
+    Ok(UNIX_EPOCH + Duration::new(secs as u64, nanos))
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The inverse of `date_calc`: counts the days from the UNIX epoch to the
+/// given date. Like `date_calc`, this treats March as the first month of the
+/// year, so that the leap day falls at the end of the cycle.
+fn days_since_epoch(year: i64, month: i64, mday: i64) -> i64 {
+    // The algorithm is from "chrono-Compatible Low-Level Date Algorithms"
+    // by Howard Hinnant: http://howardhinnant.github.io/date_algorithms.html
+
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + mday - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    // 1970-01-01 is day 719468 counting from 0000-03-01
+    era * 146097 + day_of_era - 719468
 }
 
 fn date_calc(secs_since_epoch: u64) -> (i64, i32, i64) {
@@ -125,3 +352,94 @@ fn date_calc(secs_since_epoch: u64) -> (i64, i32, i64) {
 
     (year, mon, mday)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_days_since_epoch_inverts_date_calc() {
+        for days in (0..2932896).step_by(17) {
+            let (year, month, mday) = date_calc(days * 86400);
+            assert_eq!(days_since_epoch(year, month as i64, mday), days as i64);
+        }
+    }
+
+    #[test]
+    fn test_parse_basic() {
+        let time = SystemTime::parse_iso8601_basic_seconds_utc(b"20191010T210709Z").unwrap();
+        assert_eq!(time, UNIX_EPOCH + Duration::from_secs(1570741629));
+
+        let mut buf = BASIC_FORMAT_SEC_UTC;
+        time.write_iso8602_basic_seconds_utc(&mut buf);
+        assert_eq!(&buf, b"20191010T210709Z");
+
+        assert!(SystemTime::parse_iso8601_basic_seconds_utc(b"20191010T210709").is_err());
+        assert!(SystemTime::parse_iso8601_basic_seconds_utc(b"20191310T210709Z").is_err());
+        assert!(SystemTime::parse_iso8601_basic_seconds_utc(b"20190229T210709Z").is_err());
+        assert!(SystemTime::parse_iso8601_basic_seconds_utc(b"20191010T250709Z").is_err());
+        assert!(SystemTime::parse_iso8601_basic_seconds_utc(b"2019101OT210709Z").is_err());
+        assert!(SystemTime::parse_iso8601_basic_seconds_utc(b"19691231T235959Z").is_err());
+    }
+
+    #[test]
+    fn test_parse_extended() {
+        let expected = UNIX_EPOCH + Duration::from_secs(1570741629);
+        assert_eq!(
+            SystemTime::parse_iso8601_extended(b"2019-10-10T21:07:09Z"),
+            Ok(expected)
+        );
+        assert_eq!(
+            SystemTime::parse_iso8601_extended(b"2019-10-10T21:07:09.250Z"),
+            Ok(expected + Duration::from_millis(250))
+        );
+        assert_eq!(
+            SystemTime::parse_iso8601_extended(b"2019-10-10T21:07:09.0000000015Z"),
+            Ok(expected + Duration::from_nanos(1))
+        );
+        assert_eq!(
+            SystemTime::parse_iso8601_extended(b"2019-10-11T06:07:09+09:00"),
+            Ok(expected)
+        );
+        assert_eq!(
+            SystemTime::parse_iso8601_extended(b"2019-10-10T15:37:09-05:30"),
+            Ok(expected)
+        );
+        assert_eq!(
+            SystemTime::parse_iso8601_extended(b"2020-02-29T00:00:00Z"),
+            Ok(UNIX_EPOCH + Duration::from_secs(1582934400))
+        );
+
+        let mut buf = EXTENDED_FORMAT_MILLIS_UTC;
+        (expected + Duration::from_millis(250)).write_iso8601_extended_millis_utc(&mut buf);
+        assert_eq!(&buf, b"2019-10-10T21:07:09.250Z");
+        assert_eq!(
+            SystemTime::parse_iso8601_extended(&buf),
+            Ok(expected + Duration::from_millis(250))
+        );
+
+        assert!(SystemTime::parse_iso8601_extended(b"2019-10-10T21:07:09").is_err());
+        assert!(SystemTime::parse_iso8601_extended(b"2019-10-10T21:07:09.Z").is_err());
+        assert!(SystemTime::parse_iso8601_extended(b"2019-10-10 21:07:09Z").is_err());
+        assert!(SystemTime::parse_iso8601_extended(b"2019-10-10T21:07:09+0900").is_err());
+        assert!(SystemTime::parse_iso8601_extended(b"2019-10-10T21:07:09Zjunk").is_err());
+        assert!(SystemTime::parse_iso8601_extended(b"1970-01-01T00:00:00+00:01").is_err());
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        assert_eq!(
+            SystemTime::parse_http_date(b"Thu, 10 Oct 2019 21:07:09 GMT"),
+            Ok(UNIX_EPOCH + Duration::from_secs(1570741629))
+        );
+        assert_eq!(
+            SystemTime::parse_http_date(b"Sun, 06 Nov 1994 08:49:37 GMT"),
+            Ok(UNIX_EPOCH + Duration::from_secs(784111777))
+        );
+
+        assert!(SystemTime::parse_http_date(b"Sun, 06 Nov 1994 08:49:37 UTC").is_err());
+        assert!(SystemTime::parse_http_date(b"Sunday, 06-Nov-94 08:49:37 GMT").is_err());
+        assert!(SystemTime::parse_http_date(b"Sun, 06 Non 1994 08:49:37 GMT").is_err());
+        assert!(SystemTime::parse_http_date(b"Sun, 31 Nov 1994 08:49:37 GMT").is_err());
+    }
+}
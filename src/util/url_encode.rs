@@ -46,6 +46,35 @@ pub fn encode(buffer: &mut Vec<u8>, input: &[u8], chart: &AsciiChart) {
     buffer.extend_from_slice(&input[streak_start..]);
 }
 
+/// Decodes percent-encoded input. Malformed escapes are kept as they are.
+pub fn decode(buffer: &mut Vec<u8>, input: &[u8]) {
+    let hex_value = |byte: u8| (byte as char).to_digit(16).map(|value| value as u8);
+    let mut i = 0;
+    while i < input.len() {
+        let escaped = match input.get(i..i + 3) {
+            Some([b'%', high, low]) => hex_value(*high).zip(hex_value(*low)),
+            _ => None,
+        };
+        match escaped {
+            Some((high, low)) => {
+                buffer.push(high << 4 | low);
+                i += 3;
+            }
+            None => {
+                buffer.push(input[i]);
+                i += 1;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_decode() {
+    let mut buffer = Vec::new();
+    decode(&mut buffer, b"/photos%2F2019/caf%C3%A9%20au%20lait%zz%4");
+    assert_eq!(&buffer[..], "/photos/2019/café au lait%zz%4".as_bytes());
+}
+
 pub fn ordering(left: &&[u8], right: &&[u8]) -> Ordering {
     let chart = &URL_SAFE;
 
@@ -0,0 +1,94 @@
+/// Finds the first `<tag>...</tag>` element and returns its contents as-is.
+/// This is enough for the flat, namespace-free error and response documents
+/// AWS returns; it doesn't handle attributes, CDATA or nested elements of the
+/// same name.
+pub fn find_element<'a>(xml: &'a [u8], tag: &[u8]) -> Option<&'a [u8]> {
+    let start = find_tag(xml, b"<", tag)? + tag.len() + 2;
+    let len = find_tag(&xml[start..], b"</", tag)?;
+    Some(&xml[start..start + len])
+}
+
+/// Writes text content, escaping the characters that would be markup.
+pub fn write_escaped(buffer: &mut Vec<u8>, text: &[u8]) {
+    for &byte in text {
+        match byte {
+            b'&' => buffer.extend_from_slice(b"&amp;"),
+            b'<' => buffer.extend_from_slice(b"&lt;"),
+            b'>' => buffer.extend_from_slice(b"&gt;"),
+            _ => buffer.push(byte),
+        }
+    }
+}
+
+/// Decodes text content: the predefined entities and character references.
+/// Returns `None` if an entity is unknown or unterminated.
+pub fn unescape(text: &[u8]) -> Option<Vec<u8>> {
+    let mut unescaped = Vec::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.iter().position(|&byte| byte == b'&') {
+        unescaped.extend_from_slice(&rest[..amp]);
+        let len = rest[amp..].iter().position(|&byte| byte == b';')?;
+        let entity = &rest[amp + 1..amp + len];
+        match entity {
+            b"amp" => unescaped.push(b'&'),
+            b"lt" => unescaped.push(b'<'),
+            b"gt" => unescaped.push(b'>'),
+            b"quot" => unescaped.push(b'"'),
+            b"apos" => unescaped.push(b'\''),
+            _ => {
+                let digits = std::str::from_utf8(entity.strip_prefix(b"#")?).ok()?;
+                let code = match digits.strip_prefix('x') {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => digits.parse().ok()?,
+                };
+                let c = char::from_u32(code)?;
+                unescaped.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+        }
+        rest = &rest[amp + len + 1..];
+    }
+    unescaped.extend_from_slice(rest);
+    Some(unescaped)
+}
+
+fn find_tag(haystack: &[u8], opening: &[u8], tag: &[u8]) -> Option<usize> {
+    let tag_len = opening.len() + tag.len() + 1;
+    haystack.windows(tag_len).position(|w| {
+        w.starts_with(opening) && &w[opening.len()..tag_len - 1] == tag && w[tag_len - 1] == b'>'
+    })
+}
+
+#[test]
+fn test_find_element() {
+    let xml = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<Error><Code>RequestTimeTooSkewed</Code><Message>The difference between the request time and the current time is too large.</Message><RequestTime>20191010T210709Z</RequestTime><ServerTime>2019-10-10T21:27:09Z</ServerTime><MaxAllowedSkewMilliseconds>900000</MaxAllowedSkewMilliseconds></Error>";
+    assert_eq!(
+        find_element(xml, b"Code"),
+        Some(&b"RequestTimeTooSkewed"[..])
+    );
+    assert_eq!(
+        find_element(xml, b"ServerTime"),
+        Some(&b"2019-10-10T21:27:09Z"[..])
+    );
+    assert_eq!(find_element(xml, b"Time"), None);
+    assert_eq!(find_element(b"<Code>unterminated", b"Code"), None);
+}
+
+#[test]
+fn test_write_escaped() {
+    let mut buffer = Vec::new();
+    write_escaped(&mut buffer, b"\"a&b\" <c>");
+    assert_eq!(&buffer[..], &b"\"a&amp;b\" &lt;c&gt;"[..]);
+}
+
+#[test]
+fn test_unescape() {
+    assert_eq!(
+        unescape(b"&quot;a&amp;b&quot; &lt;c&gt; &#233;&#x2F;&apos;"),
+        Some("\"a&b\" <c> \u{e9}/'".as_bytes().to_vec())
+    );
+    assert_eq!(unescape(b"plain"), Some(b"plain".to_vec()));
+    assert_eq!(unescape(b"a &amp b"), None);
+    assert_eq!(unescape(b"&nbsp;"), None);
+    assert_eq!(unescape(b"&#xD800;"), None);
+}
@@ -1,12 +1,18 @@
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
 use std::ops::Not;
+use std::str::FromStr;
 
-use super::util::write_sha256_hex;
+use super::util::{decode_lowercase_hex, write_sha256_hex, ParseHexError};
 
 pub(super) const EMPTY_STR_SHA256: &[u8; 64] =
     b"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
 
-#[derive(Clone)]
+/// Stands in for the payload hash when the payload isn't signed, like when
+/// it's streamed. Only S3 accepts it.
+pub(crate) const UNSIGNED_PAYLOAD: &[u8] = b"UNSIGNED-PAYLOAD";
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Hash(pub(super) [u8; 64]); // As hex
 
 #[derive(Copy, Clone, Debug)]
@@ -28,11 +34,48 @@ impl Hash {
     }
 }
 
+/// Parses a hash from lowercase hex.
+impl TryFrom<&[u8]> for Hash {
+    type Error = ParseHexError;
+
+    fn try_from(hex: &[u8]) -> Result<Hash, ParseHexError> {
+        // Decoding validates the hex; the hash itself is kept as hex
+        decode_lowercase_hex(hex, &mut [0; 32])?;
+        Ok(Hash(hex.try_into().expect("length checked by decoding")))
+    }
+}
+
+impl FromStr for Hash {
+    type Err = ParseHexError;
+
+    fn from_str(hex: &str) -> Result<Hash, ParseHexError> {
+        Hash::try_from(hex.as_bytes())
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(std::str::from_utf8(&self.0).expect("always hex"))
+    }
+}
+
 #[test]
 fn test_hash_empty() {
     assert_eq!(&Hash::new(b"").as_hex()[..], &EMPTY_STR_SHA256[..]);
 }
 
+#[test]
+fn test_hash_parse() {
+    let hash = Hash::new(b"abc");
+    assert_eq!(hash.to_string().parse::<Hash>(), Ok(hash.clone()));
+    assert_eq!(Hash::try_from(&hash.as_hex()[..]), Ok(hash));
+    assert_eq!(
+        "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855".parse::<Hash>(),
+        Err(ParseHexError)
+    );
+    assert_eq!("e3b0c442".parse::<Hash>(), Err(ParseHexError));
+}
+
 impl Signature {
     pub fn as_array(&self) -> &[u8; 32] {
         self.0.as_ref().try_into().expect("always succeeds")
@@ -45,6 +88,82 @@ impl Signature {
     pub fn write_hex(&self, output_buf: &mut [u8; 64]) {
         super::util::write_sha256_hex(self.as_array(), output_buf);
     }
+
+    /// Checks in constant time that `hex` is this signature in lowercase hex,
+    /// as it appears in the `Authorization` header and `X-Amz-Signature`.
+    pub fn verify_hex(&self, hex: &[u8]) -> bool {
+        let mut own_hex = [0; 64];
+        self.write_hex(&mut own_hex);
+        ring::constant_time::verify_slices_are_equal(&own_hex, hex).is_ok()
+    }
+
+    /// Checks in constant time that `bytes` is this signature.
+    pub fn verify_bytes(&self, bytes: &[u8]) -> bool {
+        ring::constant_time::verify_slices_are_equal(self.as_bytes(), bytes).is_ok()
+    }
+}
+
+/// Parses a signature from lowercase hex, the way it's sent in the
+/// `Authorization` header and the `X-Amz-Signature` query parameter.
+impl TryFrom<&[u8]> for Signature {
+    type Error = ParseHexError;
+
+    fn try_from(hex: &[u8]) -> Result<Signature, ParseHexError> {
+        let mut signature = Signature([0; 32]);
+        decode_lowercase_hex(hex, &mut signature.0)?;
+        Ok(signature)
+    }
+}
+
+impl FromStr for Signature {
+    type Err = ParseHexError;
+
+    fn from_str(hex: &str) -> Result<Signature, ParseHexError> {
+        Signature::try_from(hex.as_bytes())
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut hex = [0; 64];
+        self.write_hex(&mut hex);
+        f.write_str(std::str::from_utf8(&hex).expect("always hex"))
+    }
+}
+
+#[test]
+fn test_signature_parse() {
+    let hex = "5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7";
+    let signature: Signature = hex.parse().unwrap();
+    assert_eq!(signature.to_string(), hex);
+    assert!(signature.verify_hex(hex.as_bytes()));
+    assert_eq!(
+        Signature::try_from(hex.as_bytes()).unwrap().as_array(),
+        signature.as_array()
+    );
+    assert!(hex.to_uppercase().parse::<Signature>().is_err());
+    assert!(hex[1..].parse::<Signature>().is_err());
+    assert!(hex[2..].parse::<Signature>().is_err());
+}
+
+#[test]
+fn test_signature_verify() {
+    let signature = Signature(*b"\x5d\x67\x2d\x79\xc1\x5b\x13\x16\x2d\x92\x79\xb0\x85\x5c\xfb\xa6\x78\x9a\x8e\xdb\x4c\x82\xc4\x00\xe0\x6b\x59\x24\xa6\xf2\xb5\xd7");
+    assert!(
+        signature.verify_hex(b"5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7")
+    );
+    assert!(
+        !signature.verify_hex(b"5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d8")
+    );
+    assert!(
+        !signature.verify_hex(b"5D672D79C15B13162D9279B0855CFBA6789A8EDB4C82C400E06B5924A6F2B5D7")
+    );
+    assert!(
+        !signature.verify_hex(b"5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5")
+    );
+    assert!(signature.verify_bytes(&signature.0));
+    assert!(!signature.verify_bytes(&signature.0[..31]));
+    assert!(!signature.verify_bytes(&[0; 32]));
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
@@ -53,6 +172,8 @@ pub enum HttpMethod {
     Post,
     Put,
     Delete,
+    Head,
+    Patch,
 }
 
 impl HttpMethod {
@@ -63,6 +184,8 @@ impl HttpMethod {
             Post => b"POST",
             Put => b"PUT",
             Delete => b"DELETE",
+            Head => b"HEAD",
+            Patch => b"PATCH",
         }
     }
 }
@@ -119,6 +242,7 @@ impl AwsRegion {
         self.to_str().as_bytes()
     }
 
+    #[allow(clippy::result_unit_err)]
     pub fn try_from<S>(bytes: &S) -> Result<AwsRegion, ()>
     where
         S: AsRef<[u8]>,
@@ -159,6 +283,22 @@ impl From<AwsRegion> for &'static [u8] {
 pub enum AwsService {
     S3,
     Iam,
+    Sts,
+    /// IAM database authentication for RDS and Aurora.
+    RdsDb,
+    /// IAM access control for MSK clusters.
+    KafkaCluster,
+    /// IAM authentication for ElastiCache caches.
+    Elasticache,
+    /// IAM authentication for MemoryDB clusters.
+    MemoryDb,
+    /// API Gateway APIs with `AWS_IAM` authorization.
+    ExecuteApi,
+    /// Lambda function URLs with `AWS_IAM` authorization.
+    Lambda,
+    /// MQTT over WebSocket to AWS IoT Core.
+    IotDeviceGateway,
+    Transcribe,
 }
 
 impl AwsService {
@@ -167,6 +307,15 @@ impl AwsService {
         match self {
             S3 => "s3",
             Iam => "iam",
+            Sts => "sts",
+            RdsDb => "rds-db",
+            KafkaCluster => "kafka-cluster",
+            Elasticache => "elasticache",
+            MemoryDb => "memorydb",
+            ExecuteApi => "execute-api",
+            Lambda => "lambda",
+            IotDeviceGateway => "iotdevicegateway",
+            Transcribe => "transcribe",
         }
     }
 